@@ -2,6 +2,11 @@ use crate::features::extract_features;
 use crate::isolation_forest::IsolationForest;
 use crate::parser::NetworkEvent;
 use crate::reporter::AnomalyReport;
+use crate::suppression::SuppressionRules;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
 
 /// Configuration for the anomaly detector.
 pub struct DetectorConfig {
@@ -9,6 +14,12 @@ pub struct DetectorConfig {
     pub buffer_size: usize,
     pub threshold: f64,
     pub retrain_interval: usize,
+    /// Most false positives kept; beyond it the oldest are forgotten and
+    /// alerted on again.
+    pub max_false_positives: usize,
+    /// Relative difference in bytes and duration within which an event on
+    /// a false positive's flow counts as a repeat of it.
+    pub false_positive_tolerance: f64,
 }
 
 impl Default for DetectorConfig {
@@ -18,19 +29,34 @@ impl Default for DetectorConfig {
             buffer_size: 256,
             threshold: 0.65,
             retrain_interval: 1000,
+            max_false_positives: 1000,
+            false_positive_tolerance: 0.2,
         }
     }
 }
 
 /// Orchestrates the anomaly detection pipeline:
-/// buffering → training → scoring → reporting.
+/// buffering → training → scoring → suppression → reporting.
+///
+/// All learned state and counters can be checkpointed to disk with
+/// `save_checkpoint` and restored with `load_checkpoint`. Configuration
+/// and suppression rules are not part of the checkpoint; they are
+/// supplied again on startup.
+#[derive(Serialize, Deserialize)]
 pub struct Detector {
+    #[serde(skip)]
     config: DetectorConfig,
     forest: Option<IsolationForest>,
     buffer: Vec<Vec<f64>>,
+    /// Events an analyst marked as false positives, oldest first. Always
+    /// included in training data; repeats of them are not alerted on.
+    false_positives: Vec<NetworkEvent>,
+    #[serde(skip)]
+    rules: SuppressionRules,
     events_since_train: usize,
     total_events: usize,
     total_anomalies: usize,
+    total_suppressed: usize,
 }
 
 impl Detector {
@@ -39,16 +65,76 @@ impl Detector {
             config,
             forest: None,
             buffer: Vec::new(),
+            false_positives: Vec::new(),
+            rules: SuppressionRules::default(),
             events_since_train: 0,
             total_events: 0,
             total_anomalies: 0,
+            total_suppressed: 0,
         }
     }
 
+    /// Restore a detector from a checkpoint written by `save_checkpoint`,
+    /// using `config` for all settings.
+    pub fn load_checkpoint(config: DetectorConfig, path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut detector: Detector = serde_json::from_reader(reader)?;
+        detector.config = config;
+        Ok(detector)
+    }
+
+    /// Write the full detector state (model, training buffer, feedback and
+    /// counters) to `path`.
+    ///
+    /// The checkpoint is written to a temporary file and renamed into place,
+    /// so a crash mid-write never leaves a truncated checkpoint behind.
+    pub fn save_checkpoint(&self, path: &Path) -> io::Result<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            serde_json::to_writer(&mut writer, self)?;
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Replace the set of rules used to suppress known-benign anomalies.
+    pub fn set_suppression_rules(&mut self, rules: SuppressionRules) {
+        self.rules = rules;
+    }
+
+    /// Record a batch of analyst-confirmed false positives.
+    ///
+    /// Events that do not repeat a known false positive are added to the
+    /// training data, and future repeats of them are not reported. If any
+    /// were added and a model exists, it is retrained once for the whole
+    /// batch. Returns the number of events added.
+    pub fn mark_false_positives(&mut self, events: &[NetworkEvent]) -> usize {
+        let mut added = 0;
+        for event in events {
+            if self.repeats_false_positive(event) {
+                continue;
+            }
+            self.false_positives.push(event.clone());
+            added += 1;
+        }
+        let excess = self
+            .false_positives
+            .len()
+            .saturating_sub(self.config.max_false_positives);
+        self.false_positives.drain(..excess);
+
+        if added > 0 && self.forest.is_some() {
+            self.train();
+        }
+        added
+    }
+
     /// Process a single network event.
     ///
     /// Returns `Some(AnomalyReport)` if the event is anomalous,
-    /// `None` if normal or still buffering.
+    /// `None` if normal, suppressed or still buffering.
     pub fn process(&mut self, event: &NetworkEvent) -> Option<AnomalyReport> {
         let features = extract_features(event);
         self.total_events += 1;
@@ -80,6 +166,10 @@ impl Detector {
         }
 
         if score >= self.config.threshold {
+            if self.is_suppressed(event) {
+                self.total_suppressed += 1;
+                return None;
+            }
             self.total_anomalies += 1;
             Some(AnomalyReport {
                 event: event.clone(),
@@ -91,9 +181,27 @@ impl Detector {
         }
     }
 
+    fn is_suppressed(&self, event: &NetworkEvent) -> bool {
+        self.rules.matching(event).is_some() || self.repeats_false_positive(event)
+    }
+
+    /// Whether `event` is on a false positive's flow with bytes and duration
+    /// close to it. Other traffic on the flow is still scored and reported.
+    fn repeats_false_positive(&self, event: &NetworkEvent) -> bool {
+        let tolerance = self.config.false_positive_tolerance;
+        self.false_positives.iter().any(|fp| {
+            same_flow(fp, event)
+                && within(fp.bytes as f64, event.bytes as f64, tolerance)
+                && within(fp.duration, event.duration, tolerance)
+        })
+    }
+
     fn train(&mut self) {
+        let mut data = self.buffer.clone();
+        data.extend(self.false_positives.iter().map(extract_features));
+
         self.forest = Some(IsolationForest::fit(
-            &self.buffer,
+            &data,
             self.config.n_trees,
             self.config.buffer_size,
         ));
@@ -108,7 +216,109 @@ impl Detector {
         self.total_anomalies
     }
 
+    pub fn total_suppressed(&self) -> usize {
+        self.total_suppressed
+    }
+
     pub fn is_trained(&self) -> bool {
         self.forest.is_some()
     }
 }
+
+/// Whether `a` and `b` differ by at most `tolerance` of the larger.
+fn within(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= tolerance * a.abs().max(b.abs())
+}
+
+/// Two events belong to the same flow if they share endpoints, service port
+/// and protocol. The ephemeral source port is ignored.
+fn same_flow(a: &NetworkEvent, b: &NetworkEvent) -> bool {
+    a.src_ip == b.src_ip
+        && a.dst_ip == b.dst_ip
+        && a.dst_port == b.dst_port
+        && a.protocol == b.protocol
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{generate, GeneratorConfig};
+
+    fn config() -> DetectorConfig {
+        DetectorConfig {
+            n_trees: 50,
+            buffer_size: 128,
+            // No retraining while the two detectors are compared.
+            retrain_interval: usize::MAX,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let events = generate(&GeneratorConfig {
+            baseline_events: 400,
+            ..Default::default()
+        });
+        let (warmup, rest) = events.split_at(200);
+
+        let mut detector = Detector::new(config());
+        for event in warmup {
+            detector.process(event);
+        }
+        assert!(detector.is_trained());
+        detector.mark_false_positives(&rest[..1]);
+
+        let path = std::env::temp_dir().join(format!("detector-{}.json", std::process::id()));
+        detector.save_checkpoint(&path).unwrap();
+        let mut restored = Detector::load_checkpoint(config(), &path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.total_events(), detector.total_events());
+        for event in rest {
+            let expected = detector.process(event).map(|r| r.score);
+            let actual = restored.process(event).map(|r| r.score);
+            assert_eq!(actual, expected);
+        }
+        assert!(detector.total_anomalies() > 0);
+        assert_eq!(restored.total_anomalies(), detector.total_anomalies());
+        assert_eq!(restored.total_suppressed(), detector.total_suppressed());
+    }
+
+    #[test]
+    fn test_false_positive_repeats_are_suppressed_and_capped() {
+        let event = generate(&GeneratorConfig {
+            baseline_events: 1,
+            ..Default::default()
+        })
+        .remove(0);
+        let on_port = |port| NetworkEvent {
+            dst_port: port,
+            ..event.clone()
+        };
+
+        let mut detector = Detector::new(DetectorConfig {
+            max_false_positives: 2,
+            ..config()
+        });
+        // A near-identical event on the same flow adds nothing.
+        let mut repeat = on_port(1);
+        repeat.bytes += repeat.bytes / 10;
+        assert_eq!(
+            detector.mark_false_positives(&[on_port(1), repeat.clone()]),
+            1
+        );
+        assert_eq!(detector.mark_false_positives(&[on_port(1)]), 0);
+        assert!(detector.is_suppressed(&repeat));
+
+        // Very different traffic on the same flow is still reported.
+        let mut transfer = on_port(1);
+        transfer.bytes = transfer.bytes * 100 + 1;
+        assert!(!detector.is_suppressed(&transfer));
+
+        assert_eq!(detector.mark_false_positives(&[on_port(2), on_port(3)]), 2);
+        assert_eq!(detector.false_positives.len(), 2);
+        assert!(!detector.is_suppressed(&on_port(1)));
+        assert!(detector.is_suppressed(&on_port(3)));
+    }
+}
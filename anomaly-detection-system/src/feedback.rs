@@ -0,0 +1,128 @@
+use crate::parser::NetworkEvent;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// A line in the feedback file: either a full anomaly report copied from
+/// the JSON output, or a bare network event.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FeedbackEntry {
+    Report { event: NetworkEvent },
+    Event(NetworkEvent),
+}
+
+/// Tails a JSON Lines file of events that an analyst has marked as false positives.
+///
+/// The expected workflow is to move lines from the anomaly output file
+/// (see `reporter::write_json`) into the feedback file. Each call to `poll`
+/// returns only the events appended since the previous call.
+pub struct FeedbackLog {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl FeedbackLog {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            offset: 0,
+        }
+    }
+
+    /// Read events appended since the last poll.
+    ///
+    /// A missing file means no feedback yet. Malformed lines are skipped
+    /// with a warning; a trailing line without a newline is left for the
+    /// next poll since it may still be being written.
+    pub fn poll(&mut self) -> io::Result<Vec<NetworkEvent>> {
+        let mut file = match File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        // The file was truncated or replaced: start over. The detector
+        // ignores events it has already seen.
+        if file.metadata()?.len() < self.offset {
+            self.offset = 0;
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        let mut events = Vec::new();
+        for line in contents.split_inclusive('\n') {
+            if !line.ends_with('\n') {
+                break;
+            }
+            self.offset += line.len() as u64;
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match serde_json::from_str::<FeedbackEntry>(line) {
+                Ok(FeedbackEntry::Report { event }) | Ok(FeedbackEntry::Event(event)) => {
+                    events.push(event)
+                }
+                Err(e) => eprintln!("[WARN] Skipping malformed feedback line: {}", e),
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_line;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    fn append(path: &Path, contents: &str) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_poll_reads_reports_and_events() {
+        let path = std::env::temp_dir().join(format!("feedback-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut log = FeedbackLog::new(&path);
+        assert!(log.poll().unwrap().is_empty());
+
+        let first = parse_line("2024-01-15T10:30:00 192.168.1.10 54321 10.0.0.1 443 TCP 1500 0.05")
+            .unwrap();
+        let second =
+            parse_line("2024-01-15T10:31:00 192.168.1.11 40000 10.0.0.2 53 UDP 80 0.01").unwrap();
+        let event = |e: &NetworkEvent| serde_json::to_string(e).unwrap();
+        append(
+            &path,
+            &format!(
+                "# copied from anomalies.json\n{{\"event\": {}, \"score\": 0.8}}\nnot json\n{}\n{}",
+                event(&first),
+                event(&second),
+                &event(&first)[..10]
+            ),
+        );
+        assert_eq!(log.poll().unwrap(), vec![first.clone(), second]);
+
+        // The partial last line is read once it is complete.
+        append(&path, &format!("{}\n", &event(&first)[10..]));
+        assert_eq!(log.poll().unwrap(), vec![first.clone()]);
+        assert!(log.poll().unwrap().is_empty());
+
+        // A truncated file is read again from the start.
+        fs::write(&path, format!("{}\n", event(&first))).unwrap();
+        assert_eq!(log.poll().unwrap(), vec![first]);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,402 @@
+use crate::parser::{NetworkEvent, Protocol};
+use chrono::{Duration, NaiveDateTime, Timelike};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::str::FromStr;
+
+/// Label attached to baseline (benign) traffic.
+pub const NORMAL_LABEL: &str = "normal";
+
+/// An attack pattern that can be injected into generated traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// One host probing a sequential range of ports on an internal target.
+    PortScan,
+    /// Large, long-lived transfers to an external host.
+    Exfiltration,
+    /// A burst of oversized DNS queries to a single resolver.
+    DnsTunneling,
+    /// Small, evenly spaced check-ins to a command-and-control server.
+    Beaconing,
+    /// Many external sources flooding one internal service in a few seconds.
+    DdosBurst,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 5] = [
+        Scenario::PortScan,
+        Scenario::Exfiltration,
+        Scenario::DnsTunneling,
+        Scenario::Beaconing,
+        Scenario::DdosBurst,
+    ];
+
+    /// Label written for events belonging to this scenario.
+    pub fn label(self) -> &'static str {
+        match self {
+            Scenario::PortScan => "port_scan",
+            Scenario::Exfiltration => "exfiltration",
+            Scenario::DnsTunneling => "dns_tunneling",
+            Scenario::Beaconing => "beaconing",
+            Scenario::DdosBurst => "ddos_burst",
+        }
+    }
+}
+
+impl fmt::Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl FromStr for Scenario {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "port_scan" => Ok(Scenario::PortScan),
+            "exfiltration" => Ok(Scenario::Exfiltration),
+            "dns_tunneling" => Ok(Scenario::DnsTunneling),
+            "beaconing" => Ok(Scenario::Beaconing),
+            "ddos_burst" => Ok(Scenario::DdosBurst),
+            _ => Err(format!(
+                "unknown scenario '{}' (expected one of: port-scan, exfiltration, dns-tunneling, beaconing, ddos-burst)",
+                s
+            )),
+        }
+    }
+}
+
+/// Configuration for the synthetic traffic generator.
+pub struct GeneratorConfig {
+    /// RNG seed; the same seed always yields the same traffic.
+    pub seed: u64,
+    /// Number of baseline (normal) events.
+    pub baseline_events: usize,
+    /// Timestamp of the first baseline event.
+    pub start: NaiveDateTime,
+    /// Attack scenarios to inject, each once.
+    pub scenarios: Vec<Scenario>,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            baseline_events: 2000,
+            start: NaiveDateTime::parse_from_str("2024-01-15T09:00:00", "%Y-%m-%dT%H:%M:%S")
+                .unwrap(),
+            scenarios: Scenario::ALL.to_vec(),
+        }
+    }
+}
+
+const CLIENTS: [&str; 8] = [
+    "192.168.1.10",
+    "192.168.1.11",
+    "192.168.1.12",
+    "192.168.1.13",
+    "192.168.1.14",
+    "192.168.1.15",
+    "192.168.1.16",
+    "192.168.1.17",
+];
+
+const WEB_SERVERS: [&str; 6] = [
+    "93.184.216.34",
+    "172.217.14.206",
+    "151.101.1.69",
+    "104.244.42.1",
+    "52.94.236.248",
+    "140.82.112.3",
+];
+
+const RESOLVERS: [&str; 2] = ["8.8.8.8", "8.8.4.4"];
+
+const INTERNAL_SERVERS: [&str; 3] = ["10.0.0.1", "10.0.0.5", "10.0.0.80"];
+
+/// Generate labeled traffic: baseline events with the configured attack
+/// scenarios injected, sorted by timestamp.
+///
+/// Attacks are placed in the second half of the timeline so that a
+/// detector reading the output in order trains on mostly clean traffic.
+pub fn generate(config: &GeneratorConfig) -> Vec<NetworkEvent> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut events = Vec::with_capacity(config.baseline_events);
+
+    let mut ts = config.start;
+    for _ in 0..config.baseline_events {
+        ts += Duration::milliseconds(rng.gen_range(500..3500));
+        events.push(baseline_event(&mut rng, ts));
+    }
+
+    let span_secs = (ts - config.start).num_seconds().max(1);
+    for &scenario in &config.scenarios {
+        let offset = rng.gen_range(span_secs / 2..=span_secs);
+        let at = config.start + Duration::seconds(offset);
+        events.extend(attack_events(&mut rng, scenario, at));
+    }
+
+    // Stable sort keeps each scenario's events in generation order within a second.
+    events.sort_by_key(|e| e.timestamp);
+    events
+}
+
+fn baseline_event(rng: &mut StdRng, ts: NaiveDateTime) -> NetworkEvent {
+    let src_ip = CLIENTS.choose(rng).unwrap();
+    let src_port = rng.gen_range(49152..=60999);
+
+    let roll: f64 = rng.gen();
+    let (dst_ip, dst_port, protocol, bytes, duration) = if roll < 0.25 {
+        // DNS lookup
+        (
+            *RESOLVERS.choose(rng).unwrap(),
+            53,
+            Protocol::Udp,
+            rng.gen_range(50..=120),
+            rng.gen_range(0.005..0.03),
+        )
+    } else if roll < 0.30 {
+        // Larger HTTPS download (software update, media)
+        (
+            *WEB_SERVERS.choose(rng).unwrap(),
+            443,
+            Protocol::Tcp,
+            rng.gen_range(50_000..=2_000_000),
+            rng.gen_range(0.5..8.0),
+        )
+    } else if roll < 0.35 {
+        // Internal services: SSH and intranet web
+        let (dst, port) = if rng.gen_bool(0.5) {
+            (INTERNAL_SERVERS[0], 22)
+        } else {
+            (INTERNAL_SERVERS[2], 80)
+        };
+        (
+            dst,
+            port,
+            Protocol::Tcp,
+            rng.gen_range(200..=8000),
+            rng.gen_range(0.01..0.5),
+        )
+    } else {
+        // Regular HTTPS browsing
+        (
+            *WEB_SERVERS.choose(rng).unwrap(),
+            443,
+            Protocol::Tcp,
+            rng.gen_range(500..=3000),
+            rng.gen_range(0.02..0.12),
+        )
+    };
+
+    event(ts, src_ip, src_port, dst_ip, dst_port, protocol, bytes, duration, NORMAL_LABEL)
+}
+
+fn attack_events(rng: &mut StdRng, scenario: Scenario, start: NaiveDateTime) -> Vec<NetworkEvent> {
+    let label = scenario.label();
+    let mut events = Vec::new();
+
+    match scenario {
+        Scenario::PortScan => {
+            let attacker = format!("192.168.1.{}", rng.gen_range(100..=200));
+            let target = *INTERNAL_SERVERS.choose(rng).unwrap();
+            let first_port: u16 = rng.gen_range(1..=1000);
+            let count: u16 = rng.gen_range(50..=150);
+            let src_port = rng.gen_range(40000..=65000);
+            let mut ts = start;
+            for i in 0..count {
+                ts += Duration::milliseconds(rng.gen_range(20..60));
+                events.push(event(
+                    ts,
+                    &attacker,
+                    src_port,
+                    target,
+                    first_port + i,
+                    Protocol::Tcp,
+                    rng.gen_range(40..=60),
+                    rng.gen_range(0.0005..0.002),
+                    label,
+                ));
+            }
+        }
+        Scenario::Exfiltration => {
+            let src = *CLIENTS.choose(rng).unwrap();
+            let dst = format!("45.33.{}.{}", rng.gen_range(0..=255), rng.gen_range(1..=254));
+            let dst_port = *[8443u16, 4444, 2222].choose(rng).unwrap();
+            let mut ts = start;
+            for _ in 0..rng.gen_range(1..=3) {
+                let duration = rng.gen_range(60.0..600.0);
+                events.push(event(
+                    ts,
+                    src,
+                    rng.gen_range(49152..=60999),
+                    &dst,
+                    dst_port,
+                    Protocol::Tcp,
+                    rng.gen_range(5_000_000..=50_000_000),
+                    duration,
+                    label,
+                ));
+                ts += Duration::seconds(duration as i64 + rng.gen_range(5..30));
+            }
+        }
+        Scenario::DnsTunneling => {
+            let src = *CLIENTS.choose(rng).unwrap();
+            let resolver = format!("203.0.113.{}", rng.gen_range(1..=254));
+            let mut ts = start;
+            for _ in 0..rng.gen_range(100..=300) {
+                events.push(event(
+                    ts,
+                    src,
+                    rng.gen_range(49152..=60999),
+                    &resolver,
+                    53,
+                    Protocol::Udp,
+                    rng.gen_range(180..=512),
+                    rng.gen_range(0.01..0.05),
+                    label,
+                ));
+                ts += Duration::milliseconds(rng.gen_range(100..500));
+            }
+        }
+        Scenario::Beaconing => {
+            let src = *CLIENTS.choose(rng).unwrap();
+            let c2 = format!("198.51.100.{}", rng.gen_range(1..=254));
+            let dst_port = *[8080u16, 8443, 443].choose(rng).unwrap();
+            let interval_secs = rng.gen_range(30..=120);
+            let bytes = rng.gen_range(280..=350);
+            for i in 0..rng.gen_range(30..=60) {
+                let jitter_ms = rng.gen_range(-2000..=2000);
+                let ts = start
+                    + Duration::seconds(i * interval_secs)
+                    + Duration::milliseconds(jitter_ms);
+                events.push(event(
+                    ts,
+                    src,
+                    rng.gen_range(49152..=60999),
+                    &c2,
+                    dst_port,
+                    Protocol::Tcp,
+                    bytes + rng.gen_range(0..=8),
+                    rng.gen_range(0.04..0.06),
+                    label,
+                ));
+            }
+        }
+        Scenario::DdosBurst => {
+            let target = INTERNAL_SERVERS[2];
+            let protocol = if rng.gen_bool(0.5) { Protocol::Tcp } else { Protocol::Udp };
+            for _ in 0..rng.gen_range(200..=500) {
+                let src = format!(
+                    "{}.{}.{}.{}",
+                    rng.gen_range(11..=223),
+                    rng.gen_range(0..=255),
+                    rng.gen_range(0..=255),
+                    rng.gen_range(1..=254)
+                );
+                let ts = start + Duration::milliseconds(rng.gen_range(0..5000));
+                events.push(event(
+                    ts,
+                    &src,
+                    rng.gen_range(1024..=65535),
+                    target,
+                    80,
+                    protocol,
+                    rng.gen_range(60..=1500),
+                    rng.gen_range(0.0001..0.002),
+                    label,
+                ));
+            }
+        }
+    }
+
+    events
+}
+
+#[allow(clippy::too_many_arguments)]
+fn event(
+    timestamp: NaiveDateTime,
+    src_ip: &str,
+    src_port: u16,
+    dst_ip: &str,
+    dst_port: u16,
+    protocol: Protocol,
+    bytes: u64,
+    duration: f64,
+    label: &str,
+) -> NetworkEvent {
+    NetworkEvent {
+        // The line format has one-second resolution.
+        timestamp: timestamp.with_nanosecond(0).unwrap_or(timestamp),
+        src_ip: src_ip.to_string(),
+        src_port,
+        dst_ip: dst_ip.to_string(),
+        dst_port,
+        protocol,
+        bytes,
+        duration: (duration * 10_000.0).round() / 10_000.0,
+        label: Some(label.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{format_line, parse_line};
+
+    fn small_config(seed: u64) -> GeneratorConfig {
+        GeneratorConfig {
+            seed,
+            baseline_events: 300,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_same_seed_is_reproducible() {
+        let a: Vec<String> = generate(&small_config(7)).iter().map(format_line).collect();
+        let b: Vec<String> = generate(&small_config(7)).iter().map(format_line).collect();
+        let c: Vec<String> = generate(&small_config(8)).iter().map(format_line).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_all_scenarios_labeled() {
+        let events = generate(&small_config(1));
+        let normal = events
+            .iter()
+            .filter(|e| e.label.as_deref() == Some(NORMAL_LABEL))
+            .count();
+        assert_eq!(normal, 300);
+
+        for scenario in Scenario::ALL {
+            assert!(
+                events.iter().any(|e| e.label.as_deref() == Some(scenario.label())),
+                "missing events for {}",
+                scenario
+            );
+        }
+    }
+
+    #[test]
+    fn test_output_is_sorted_and_parseable() {
+        let events = generate(&small_config(3));
+        assert!(events.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+        for event in &events {
+            let parsed = parse_line(&format_line(event)).unwrap();
+            assert_eq!(&parsed, event);
+        }
+    }
+
+    #[test]
+    fn test_scenario_from_str() {
+        assert_eq!("port-scan".parse::<Scenario>().unwrap(), Scenario::PortScan);
+        assert_eq!("DDOS_BURST".parse::<Scenario>().unwrap(), Scenario::DdosBurst);
+        assert!("phishing".parse::<Scenario>().is_err());
+    }
+}
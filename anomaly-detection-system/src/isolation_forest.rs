@@ -1,4 +1,5 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 /// Average path length of unsuccessful search in a Binary Search Tree.
 /// Used to normalize the anomaly score.
@@ -10,6 +11,7 @@ fn c(n: f64) -> f64 {
 }
 
 /// A node in an isolation tree.
+#[derive(Serialize, Deserialize)]
 enum IsolationNode {
     /// Internal node: split on `feature` at `threshold`.
     Branch {
@@ -48,6 +50,7 @@ impl IsolationNode {
 }
 
 /// A single isolation tree.
+#[derive(Serialize, Deserialize)]
 pub struct IsolationTree {
     root: IsolationNode,
 }
@@ -129,6 +132,7 @@ impl IsolationTree {
 }
 
 /// An ensemble of isolation trees for anomaly detection.
+#[derive(Serialize, Deserialize)]
 pub struct IsolationForest {
     trees: Vec<IsolationTree>,
     sample_size: usize,
@@ -233,6 +237,19 @@ mod tests {
         ];
         let forest = IsolationForest::fit(&data, 50, 5);
         let score = forest.score(&[1.0, 2.0]);
-        assert!(score >= 0.0 && score <= 1.0, "Score {} out of range", score);
+        assert!((0.0..=1.0).contains(&score), "Score {} out of range", score);
+    }
+
+    #[test]
+    fn test_serialization_roundtrip() {
+        let data: Vec<Vec<f64>> = (0..64).map(|i| vec![i as f64, (i % 7) as f64]).collect();
+        let forest = IsolationForest::fit(&data, 20, 64);
+
+        let json = serde_json::to_string(&forest).unwrap();
+        let restored: IsolationForest = serde_json::from_str(&json).unwrap();
+
+        for point in [[3.0, 3.0], [500.0, -2.0]] {
+            assert_eq!(forest.score(&point), restored.score(&point));
+        }
     }
 }
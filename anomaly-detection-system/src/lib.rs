@@ -3,3 +3,6 @@ pub mod features;
 pub mod isolation_forest;
 pub mod detector;
 pub mod reporter;
+pub mod suppression;
+pub mod feedback;
+pub mod generator;
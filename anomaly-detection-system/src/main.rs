@@ -1,19 +1,68 @@
-use std::io::{self, BufRead};
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use chrono::NaiveDateTime;
+use clap::{Args, Parser, Subcommand};
 
 use anomaly_detection_system::detector::{Detector, DetectorConfig};
+use anomaly_detection_system::feedback::FeedbackLog;
+use anomaly_detection_system::generator::{self, GeneratorConfig, Scenario};
 use anomaly_detection_system::parser;
 use anomaly_detection_system::reporter;
+use anomaly_detection_system::suppression::SuppressionRules;
 
 #[derive(Parser)]
 #[command(
     name = "anomaly-detect",
     about = "Real-time network traffic anomaly detection using Isolation Forest",
-    version
+    version,
+    args_conflicts_with_subcommands = true
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    detect: DetectArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate labeled synthetic traffic with injected attack scenarios
+    Generate(GenerateArgs),
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Number of baseline (normal) events
+    #[arg(long, default_value_t = 2000)]
+    events: usize,
+
+    /// RNG seed for reproducible output
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+
+    /// Timestamp of the first event
+    #[arg(long, default_value = "2024-01-15T09:00:00")]
+    start: String,
+
+    /// Attack scenarios to inject (comma-separated). Defaults to all:
+    /// port-scan, exfiltration, dns-tunneling, beaconing, ddos-burst
+    #[arg(long, value_delimiter = ',')]
+    scenarios: Vec<Scenario>,
+
+    /// Generate baseline traffic only, without attacks
+    #[arg(long, conflicts_with = "scenarios")]
+    baseline_only: bool,
+
+    /// Write to a file instead of stdout
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DetectArgs {
     /// Number of isolation trees in the forest
     #[arg(long, default_value_t = 100)]
     trees: usize,
@@ -37,25 +86,166 @@ struct Cli {
     /// Print status every N events (0 to disable)
     #[arg(long, default_value_t = 100)]
     status_interval: usize,
+
+    /// JSON file of suppression rules for known-benign traffic
+    #[arg(long)]
+    rules: Option<PathBuf>,
+
+    /// JSON Lines file of reports marked as false positives by an analyst
+    #[arg(long)]
+    feedback: Option<PathBuf>,
+
+    /// Check the feedback file for new entries every N events
+    #[arg(long, default_value_t = 100)]
+    feedback_interval: usize,
+
+    /// Most false positives to remember; the oldest are dropped
+    #[arg(long, default_value_t = 1000)]
+    max_false_positives: usize,
+
+    /// Events on a false positive's flow whose bytes and duration are
+    /// within this fraction of it are not reported
+    #[arg(long, default_value_t = 0.2)]
+    false_positive_tolerance: f64,
+
+    /// Path to checkpoint detector state to. If it exists on startup,
+    /// detection resumes from it.
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Write a checkpoint every N events
+    #[arg(long, default_value_t = 1000)]
+    checkpoint_interval: usize,
 }
 
 fn main() {
     let cli = Cli::parse();
 
+    match cli.command {
+        Some(Command::Generate(args)) => generate(args),
+        None => detect(cli.detect),
+    }
+}
+
+fn generate(args: GenerateArgs) {
+    let start = match NaiveDateTime::parse_from_str(&args.start, "%Y-%m-%dT%H:%M:%S") {
+        Ok(ts) => ts,
+        Err(e) => {
+            eprintln!("[ERROR] Invalid --start timestamp '{}': {}", args.start, e);
+            std::process::exit(1);
+        }
+    };
+
+    let scenarios = if args.baseline_only {
+        vec![]
+    } else if args.scenarios.is_empty() {
+        Scenario::ALL.to_vec()
+    } else {
+        args.scenarios
+    };
+
+    let config = GeneratorConfig {
+        seed: args.seed,
+        baseline_events: args.events,
+        start,
+        scenarios,
+    };
+    let events = generator::generate(&config);
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => match File::create(path) {
+            Ok(f) => Box::new(BufWriter::new(f)),
+            Err(e) => {
+                eprintln!("[ERROR] Failed to create {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let result = (|| -> io::Result<()> {
+        writeln!(out, "# Synthetic network traffic (seed {})", config.seed)?;
+        writeln!(out, "# Format: timestamp src_ip src_port dst_ip dst_port protocol bytes duration label")?;
+        for event in &events {
+            writeln!(out, "{}", parser::format_line(event))?;
+        }
+        out.flush()
+    })();
+
+    match result {
+        Ok(()) => {
+            let attacks = events
+                .iter()
+                .filter(|e| e.label.as_deref() != Some(generator::NORMAL_LABEL))
+                .count();
+            eprintln!(
+                "[INFO] Generated {} events ({} attack events across {} scenarios)",
+                events.len(),
+                attacks,
+                config.scenarios.len()
+            );
+        }
+        // Downstream closed the pipe (e.g. `| head`); nothing left to do.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("[ERROR] Failed to write traffic: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn detect(args: DetectArgs) {
     let config = DetectorConfig {
-        n_trees: cli.trees,
-        buffer_size: cli.buffer_size,
-        threshold: cli.threshold,
-        retrain_interval: cli.retrain_interval,
+        n_trees: args.trees,
+        buffer_size: args.buffer_size,
+        threshold: args.threshold,
+        retrain_interval: args.retrain_interval,
+        max_false_positives: args.max_false_positives,
+        false_positive_tolerance: args.false_positive_tolerance,
+    };
+
+    let mut detector = match &args.checkpoint {
+        Some(path) if path.exists() => match Detector::load_checkpoint(config, path) {
+            Ok(d) => {
+                eprintln!(
+                    "[INFO] Resumed from checkpoint {} ({} events, {})",
+                    path.display(),
+                    d.total_events(),
+                    if d.is_trained() { "trained" } else { "buffering" }
+                );
+                d
+            }
+            Err(e) => {
+                eprintln!("[ERROR] Failed to load checkpoint {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        _ => Detector::new(config),
     };
 
-    let mut detector = Detector::new(config);
+    if let Some(path) = &args.rules {
+        match SuppressionRules::load(path) {
+            Ok(rules) => {
+                eprintln!("[INFO] Loaded {} suppression rules from {}", rules.len(), path.display());
+                detector.set_suppression_rules(rules);
+            }
+            Err(e) => {
+                eprintln!("[ERROR] Failed to load suppression rules from {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut feedback = args.feedback.as_deref().map(FeedbackLog::new);
+    if let Some(log) = feedback.as_mut() {
+        apply_feedback(&mut detector, log);
+    }
 
     eprintln!("Anomaly Detection System — Isolation Forest");
     eprintln!("============================================");
     eprintln!("Trees: {} | Threshold: {} | Buffer: {} | Retrain every: {}",
-        cli.trees, cli.threshold, cli.buffer_size, cli.retrain_interval);
-    eprintln!("Output: {}", cli.output.display());
+        args.trees, args.threshold, args.buffer_size, args.retrain_interval);
+    eprintln!("Output: {}", args.output.display());
     eprintln!("Reading from stdin... (pipe network traffic data)");
     eprintln!();
 
@@ -78,15 +268,27 @@ fn main() {
 
         if let Some(report) = detector.process(&event) {
             reporter::print_anomaly(&report);
-            if let Err(e) = reporter::write_json(&report, &cli.output) {
+            if let Err(e) = reporter::write_json(&report, &args.output) {
                 eprintln!("[WARN] Failed to write JSON: {}", e);
             }
         }
 
         line_count += 1;
 
+        if let Some(log) = feedback.as_mut() {
+            if args.feedback_interval > 0 && line_count % args.feedback_interval == 0 {
+                apply_feedback(&mut detector, log);
+            }
+        }
+
+        if let Some(path) = &args.checkpoint {
+            if args.checkpoint_interval > 0 && line_count % args.checkpoint_interval == 0 {
+                save_checkpoint(&detector, path);
+            }
+        }
+
         // Print status update
-        if cli.status_interval > 0 && line_count % cli.status_interval == 0 {
+        if args.status_interval > 0 && line_count % args.status_interval == 0 {
             reporter::print_status(
                 detector.total_events(),
                 detector.total_anomalies(),
@@ -95,10 +297,40 @@ fn main() {
         }
 
         // Notify when training completes
-        if detector.total_events() == cli.buffer_size && detector.is_trained() {
-            eprintln!("[INFO] Model trained on {} events. Now detecting anomalies.", cli.buffer_size);
+        if detector.total_events() == args.buffer_size && detector.is_trained() {
+            eprintln!("[INFO] Model trained on {} events. Now detecting anomalies.", args.buffer_size);
         }
     }
 
-    reporter::print_summary(detector.total_events(), detector.total_anomalies());
+    if let Some(path) = &args.checkpoint {
+        save_checkpoint(&detector, path);
+    }
+
+    reporter::print_summary(
+        detector.total_events(),
+        detector.total_anomalies(),
+        detector.total_suppressed(),
+    );
+}
+
+fn save_checkpoint(detector: &Detector, path: &Path) {
+    if let Err(e) = detector.save_checkpoint(path) {
+        eprintln!("[WARN] Failed to write checkpoint {}: {}", path.display(), e);
+    }
+}
+
+/// Feed newly marked false positives from the feedback file into the detector.
+fn apply_feedback(detector: &mut Detector, log: &mut FeedbackLog) {
+    let events = match log.poll() {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("[WARN] Failed to read feedback file: {}", e);
+            return;
+        }
+    };
+
+    let added = detector.mark_false_positives(&events);
+    if added > 0 {
+        eprintln!("[INFO] Marked {} event(s) as false positives", added);
+    }
 }
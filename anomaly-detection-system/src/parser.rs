@@ -1,8 +1,8 @@
 use chrono::NaiveDateTime;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NetworkEvent {
     pub timestamp: NaiveDateTime,
     pub src_ip: String,
@@ -12,13 +12,21 @@ pub struct NetworkEvent {
     pub protocol: Protocol,
     pub bytes: u64,
     pub duration: f64,
+    /// Ground-truth label from an optional ninth column, as written by
+    /// the traffic generator (e.g. `normal`, `port_scan`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Protocol {
+    #[serde(alias = "TCP", alias = "tcp")]
     Tcp,
+    #[serde(alias = "UDP", alias = "udp")]
     Udp,
+    #[serde(alias = "ICMP", alias = "icmp")]
     Icmp,
+    #[serde(alias = "OTHER", alias = "other")]
     Other,
 }
 
@@ -56,7 +64,7 @@ fn parse_protocol(s: &str) -> Protocol {
 /// Parse a single line of network traffic data.
 ///
 /// Expected format (space-separated):
-/// `timestamp src_ip src_port dst_ip dst_port protocol bytes duration`
+/// `timestamp src_ip src_port dst_ip dst_port protocol bytes duration [label]`
 ///
 /// Example:
 /// `2024-01-15T10:30:00 192.168.1.10 54321 10.0.0.1 443 TCP 1500 0.05`
@@ -79,6 +87,7 @@ pub fn parse_line(line: &str) -> Option<NetworkEvent> {
     let protocol = parse_protocol(parts[5]);
     let bytes: u64 = parts[6].parse().ok()?;
     let duration: f64 = parts[7].parse().ok()?;
+    let label = parts.get(8).map(|s| s.to_string());
 
     Some(NetworkEvent {
         timestamp,
@@ -89,9 +98,30 @@ pub fn parse_line(line: &str) -> Option<NetworkEvent> {
         protocol,
         bytes,
         duration,
+        label,
     })
 }
 
+/// Format an event as a line accepted by `parse_line`.
+pub fn format_line(event: &NetworkEvent) -> String {
+    let mut line = format!(
+        "{} {} {} {} {} {} {} {}",
+        event.timestamp.format("%Y-%m-%dT%H:%M:%S"),
+        event.src_ip,
+        event.src_port,
+        event.dst_ip,
+        event.dst_port,
+        event.protocol,
+        event.bytes,
+        event.duration,
+    );
+    if let Some(label) = &event.label {
+        line.push(' ');
+        line.push_str(label);
+    }
+    line
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,4 +158,12 @@ mod tests {
         let event = parse_line(line).unwrap();
         assert_eq!(event.protocol, Protocol::Udp);
     }
+
+    #[test]
+    fn test_parse_label_column() {
+        let line = "2024-01-15T10:30:00 10.0.0.1 12345 10.0.0.2 53 UDP 64 0.01 dns_tunneling";
+        let event = parse_line(line).unwrap();
+        assert_eq!(event.label.as_deref(), Some("dns_tunneling"));
+        assert_eq!(format_line(&event), line);
+    }
 }
@@ -1,11 +1,11 @@
 use crate::parser::NetworkEvent;
 use colored::Colorize;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::Path;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AnomalyReport {
     pub event: NetworkEvent,
     pub score: f64,
@@ -54,7 +54,7 @@ pub fn write_json(report: &AnomalyReport, output_path: &Path) -> std::io::Result
 }
 
 /// Print a summary of the detection session.
-pub fn print_summary(total_events: usize, total_anomalies: usize) {
+pub fn print_summary(total_events: usize, total_anomalies: usize, total_suppressed: usize) {
     let rate = if total_events > 0 {
         (total_anomalies as f64 / total_events as f64) * 100.0
     } else {
@@ -65,6 +65,7 @@ pub fn print_summary(total_events: usize, total_anomalies: usize) {
     eprintln!("{}", "=== Detection Summary ===".bold());
     eprintln!("Total events processed: {}", total_events);
     eprintln!("Anomalies detected:     {}", total_anomalies);
+    eprintln!("Anomalies suppressed:   {}", total_suppressed);
     eprintln!("Anomaly rate:           {:.2}%", rate);
 }
 
@@ -0,0 +1,354 @@
+use crate::parser::{NetworkEvent, Protocol};
+use chrono::{NaiveTime, Timelike};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An IP network in CIDR notation, e.g. `10.0.0.0/8` or `fd00::/8`.
+///
+/// A bare address (`192.168.1.10`) is treated as a single-host network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.network, addr) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = prefix_mask_u32(self.prefix_len);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = prefix_mask_u128(self.prefix_len);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+
+    /// Check an address given as a string, as stored in `NetworkEvent`.
+    /// Unparseable addresses never match.
+    pub fn contains_str(&self, addr: &str) -> bool {
+        addr.parse::<IpAddr>()
+            .map(|ip| self.contains(&ip))
+            .unwrap_or(false)
+    }
+}
+
+fn prefix_mask_u32(prefix_len: u8) -> u32 {
+    if prefix_len == 0 {
+        0
+    } else {
+        u32::MAX << (32 - prefix_len as u32)
+    }
+}
+
+fn prefix_mask_u128(prefix_len: u8) -> u128 {
+    if prefix_len == 0 {
+        0
+    } else {
+        u128::MAX << (128 - prefix_len as u32)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((a, p)) => (a, Some(p)),
+            None => (s, None),
+        };
+
+        let network: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid IP address in CIDR '{}'", s))?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+
+        let prefix_len = match prefix {
+            Some(p) => p
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|&p| p <= max_len)
+                .ok_or_else(|| format!("invalid prefix length in CIDR '{}'", s))?,
+            None => max_len,
+        };
+
+        Ok(Cidr {
+            network,
+            prefix_len,
+        })
+    }
+}
+
+impl TryFrom<String> for Cidr {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// An inclusive port range. Deserializes from a number (`22`)
+/// or a string (`"22"`, `"8000-8100"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "PortSpec")]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        port >= self.start && port <= self.end
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PortSpec {
+    Single(u16),
+    Text(String),
+}
+
+impl TryFrom<PortSpec> for PortRange {
+    type Error = String;
+
+    fn try_from(spec: PortSpec) -> Result<Self, Self::Error> {
+        match spec {
+            PortSpec::Single(p) => Ok(PortRange { start: p, end: p }),
+            PortSpec::Text(s) => s.parse(),
+        }
+    }
+}
+
+impl FromStr for PortRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |p: &str| {
+            p.trim()
+                .parse::<u16>()
+                .map_err(|_| format!("invalid port range '{}'", s))
+        };
+        let (start, end) = match s.split_once('-') {
+            Some((a, b)) => (parse(a)?, parse(b)?),
+            None => {
+                let p = parse(s)?;
+                (p, p)
+            }
+        };
+        if start > end {
+            return Err(format!("invalid port range '{}': start > end", s));
+        }
+        Ok(PortRange { start, end })
+    }
+}
+
+/// A daily time-of-day window, e.g. `01:00`–`03:30`.
+///
+/// If `end` is earlier than `start` the window wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct TimeWindow {
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: NaiveTime,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+fn deserialize_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&s, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(&s, "%H:%M"))
+        .map_err(|_| serde::de::Error::custom(format!("invalid time '{}', expected HH:MM", s)))
+}
+
+/// A single suppression rule. Every condition that is set must match;
+/// unset conditions match anything.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SuppressionRule {
+    pub name: String,
+    #[serde(default)]
+    pub src: Option<Cidr>,
+    #[serde(default)]
+    pub dst: Option<Cidr>,
+    #[serde(default)]
+    pub src_port: Option<PortRange>,
+    #[serde(default)]
+    pub dst_port: Option<PortRange>,
+    #[serde(default)]
+    pub protocol: Option<Protocol>,
+    #[serde(default)]
+    pub time_window: Option<TimeWindow>,
+}
+
+impl SuppressionRule {
+    pub fn matches(&self, event: &NetworkEvent) -> bool {
+        if let Some(src) = &self.src {
+            if !src.contains_str(&event.src_ip) {
+                return false;
+            }
+        }
+        if let Some(dst) = &self.dst {
+            if !dst.contains_str(&event.dst_ip) {
+                return false;
+            }
+        }
+        if let Some(ports) = &self.src_port {
+            if !ports.contains(event.src_port) {
+                return false;
+            }
+        }
+        if let Some(ports) = &self.dst_port {
+            if !ports.contains(event.dst_port) {
+                return false;
+            }
+        }
+        if let Some(protocol) = self.protocol {
+            if protocol != event.protocol {
+                return false;
+            }
+        }
+        if let Some(window) = &self.time_window {
+            let time = event.timestamp.time().with_nanosecond(0).unwrap_or_default();
+            if !window.contains(time) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// An ordered set of suppression rules, loaded from a JSON file.
+///
+/// Example:
+/// ```json
+/// [
+///   { "name": "nightly-backup", "src": "192.168.1.0/24", "dst": "10.0.5.20",
+///     "dst_port": 22, "protocol": "TCP",
+///     "time_window": { "start": "01:00", "end": "03:30" } },
+///   { "name": "internal-scanner", "src": "10.0.9.4", "dst_port": "1-1024" }
+/// ]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct SuppressionRules {
+    rules: Vec<SuppressionRule>,
+}
+
+impl SuppressionRules {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let rules = serde_json::from_str(&contents)?;
+        Ok(rules)
+    }
+
+    /// Return the first rule matching the event, if any.
+    pub fn matching(&self, event: &NetworkEvent) -> Option<&SuppressionRule> {
+        self.rules.iter().find(|rule| rule.matches(event))
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_line;
+
+    #[test]
+    fn test_cidr_contains() {
+        let cidr: Cidr = "192.168.1.0/24".parse().unwrap();
+        assert!(cidr.contains_str("192.168.1.77"));
+        assert!(!cidr.contains_str("192.168.2.1"));
+        assert!(!cidr.contains_str("not-an-ip"));
+
+        let host: Cidr = "10.0.0.1".parse().unwrap();
+        assert!(host.contains_str("10.0.0.1"));
+        assert!(!host.contains_str("10.0.0.2"));
+
+        let any: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains_str("8.8.8.8"));
+    }
+
+    #[test]
+    fn test_cidr_invalid() {
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_port_range() {
+        let range: PortRange = "8000-8100".parse().unwrap();
+        assert!(range.contains(8080));
+        assert!(!range.contains(443));
+        assert!("9000-8000".parse::<PortRange>().is_err());
+    }
+
+    #[test]
+    fn test_time_window_wraps_midnight() {
+        let window = TimeWindow {
+            start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+        };
+        assert!(window.contains(NaiveTime::from_hms_opt(23, 30, 0).unwrap()));
+        assert!(window.contains(NaiveTime::from_hms_opt(1, 0, 0).unwrap()));
+        assert!(!window.contains(NaiveTime::from_hms_opt(12, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_rules_from_json() {
+        let json = r#"[
+            { "name": "backup", "src": "192.168.1.0/24", "dst": "10.0.5.20",
+              "dst_port": 22, "protocol": "TCP",
+              "time_window": { "start": "01:00", "end": "03:30" } }
+        ]"#;
+        let rules: SuppressionRules = serde_json::from_str(json).unwrap();
+        assert_eq!(rules.len(), 1);
+
+        let backup = parse_line("2024-01-15T02:10:00 192.168.1.10 50000 10.0.5.20 22 TCP 9000000 600.0")
+            .unwrap();
+        assert_eq!(rules.matching(&backup).unwrap().name, "backup");
+
+        let daytime = parse_line("2024-01-15T14:10:00 192.168.1.10 50000 10.0.5.20 22 TCP 9000000 600.0")
+            .unwrap();
+        assert!(rules.matching(&daytime).is_none());
+
+        let udp = parse_line("2024-01-15T02:10:00 192.168.1.10 50000 10.0.5.20 22 UDP 90 0.1")
+            .unwrap();
+        assert!(rules.matching(&udp).is_none());
+    }
+}
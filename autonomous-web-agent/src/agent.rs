@@ -1,36 +1,1187 @@
-use crate::{browser::WebBrowser, extractor::InformationExtractor, safety::SafetyGuardrails, task::Task};
-use anyhow::Result;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-
-pub struct AutonomousAgent {
-    browser: Arc<WebBrowser>,
-    extractor: Arc<InformationExtractor>,
-    safety: Arc<SafetyGuardrails>,
+use crate::browser::{Browser, PageContent};
+use crate::config::{AgentConfig, RetryConfig, RuleAction};
+use crate::error::{AgentError, Result};
+use crate::extractor::ExtractedItem;
+use crate::forms::{FormMethod, FormRequest};
+use crate::frontier::Scorer;
+use crate::guardrails::Guardrails;
+use crate::planner::Planner;
+use crate::policy::PolicyDecision;
+use crate::provenance::{Decision, Link, PageRecord};
+use crate::redact::Redactor;
+use crate::report::Report;
+use crate::retry::{FetchError, FetchErrorKind};
+use crate::robots::RobotsTxt;
+use crate::sitemap::{self, Sitemap};
+use crate::state::CrawlState;
+use crate::task::{AgentAction, Task};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use url::{Host, Url};
+
+/// Progress notifications emitted while an `Agent` runs a task.
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    /// A URL passed all guardrails and is about to be fetched.
+    Visiting { url: Url, depth: usize },
+    /// The next page of a paginated series passed all guardrails and is
+    /// about to be fetched. `page` counts from 1 for the series' first page.
+    VisitingNextPage { url: Url, depth: usize, page: usize },
+    /// An allowlisted form passed all guardrails and is about to be
+    /// submitted.
+    SubmittingForm {
+        url: Url,
+        method: FormMethod,
+        depth: usize,
+    },
+    /// A page was fetched, parsed and run through the extractor.
+    PageProcessed {
+        url: Url,
+        title: Option<String>,
+        links: usize,
+        /// Items extracted from this page.
+        items: Vec<ExtractedItem>,
+        items_total: usize,
+    },
+    /// A page returned a non-200 status and was not processed.
+    HttpStatus { url: Url, status: u16 },
+    /// The request for a URL failed.
+    FetchFailed { url: Url, error: String },
+    /// A response was too large, or of a content type the task does not
+    /// process; it was skipped.
+    ContentSkipped { url: Url, reason: String },
+    /// A fetch failed transiently and will be retried after `delay`.
+    /// `attempt` counts the requests made for the URL so far.
+    Retrying {
+        url: Url,
+        attempt: u32,
+        delay: Duration,
+        reason: String,
+    },
+    /// A URL was skipped because it is deeper than the configured limit.
+    DepthExceeded { url: Url, reason: String },
+    /// A URL was skipped because its host or path prefix has used up its
+    /// share of the budget.
+    OverBudget { url: Url, reason: String },
+    /// A URL was checked against the URL policy. Denied URLs are also
+    /// reported as `UrlBlocked`.
+    PolicyDecided { decision: PolicyDecision },
+    /// A URL was rejected by the guardrails before fetching.
+    UrlBlocked { url: Url, reason: String },
+    /// A fetched page was rejected by the content guardrails.
+    ContentBlocked { url: Url, reason: String },
+    /// The request budget ran out; the agent stops.
+    BudgetExhausted { reason: String },
+    /// A sitemap was fetched and its page URLs added to the frontier.
+    SitemapProcessed { url: Url, urls_added: usize },
+    /// The frontier is empty; the agent stops.
+    FrontierExhausted,
+    /// A fetched page's text nearly matches an earlier page's; it was
+    /// skipped.
+    NearDuplicate { url: Url, duplicate_of: String },
+    /// The crawl continues from a saved state file.
+    Resumed { pages_visited: usize, frontier: usize },
+    /// The crawl state could not be saved; the crawl continues.
+    CheckpointFailed { error: String },
+    /// The agent is done with a URL; `record` says what it did and why.
+    PageRecorded { record: PageRecord },
+}
+
+type EventCallback = Box<dyn FnMut(&AgentEvent) + Send>;
+
+/// A scheduled request: a page visit, or a form submission to `url`.
+#[derive(Clone)]
+struct Visit {
+    url: Url,
+    depth: usize,
+    form: Option<FormRequest>,
+    /// Page number, for the next page of a paginated series.
+    page: Option<usize>,
+    /// Requests made for this visit so far.
+    attempts: u32,
+    /// For a retry, the earliest time it may be sent.
+    not_before: Option<Instant>,
+}
+
+/// Result of a spawned fetch, with the visit it was scheduled for and how
+/// long the request took.
+type FetchOutcome = (Visit, Result<PageContent>, Duration);
+
+/// An autonomous browsing agent that runs a single `Task` to a `Report`.
+///
+/// The agent owns its `Browser`, `Guardrails` and `Planner`, so it can be
+/// embedded in other services and driven directly:
+///
+/// ```no_run
+/// # async fn example(task: autonomous_web_agent::task::Task) -> autonomous_web_agent::error::Result<()> {
+/// use autonomous_web_agent::agent::Agent;
+/// use autonomous_web_agent::config::AgentConfig;
+///
+/// let report = Agent::new(task, AgentConfig::default())?
+///     .on_event(|event| println!("{:?}", event))
+///     .run()
+///     .await;
+/// println!("{}", report.to_json());
+/// # Ok(())
+/// # }
+/// ```
+pub struct Agent {
+    browser: Browser,
+    guardrails: Guardrails,
+    planner: Planner,
+    /// Masks sensitive data in extracted items before they are reported.
+    redactor: Redactor,
+    task_name: String,
+    /// Seed URLs whose sites' sitemaps should seed the frontier.
+    sitemap_seeds: Vec<Url>,
+    max_concurrent_requests: usize,
+    retry: RetryConfig,
+    /// Whether hosts are resolved so the policy can check their addresses.
+    resolve_hosts: bool,
+    /// Addresses of the hosts resolved so far.
+    resolved: HashMap<String, Vec<IpAddr>>,
+    /// Visits popped from the planner whose host was at its connection
+    /// cap or after the budget ran out, and failed visits waiting to be
+    /// retried.
+    deferred: VecDeque<Visit>,
+    /// Visits currently being fetched.
+    in_flight: Vec<Visit>,
+    /// Where crawl progress is saved after every page, if anywhere.
+    state_path: Option<PathBuf>,
+    resumed: bool,
+    /// Identifies this run in its report; kept across resume.
+    run_id: String,
+    errors: Vec<FetchError>,
+    pages: Vec<PageRecord>,
+    links: Vec<Link>,
+    on_event: Option<EventCallback>,
+}
+
+impl Agent {
+    pub fn new(task: Task, config: AgentConfig) -> Result<Self> {
+        let browser = Browser::new(&config)?;
+        let task_name = task.name.clone();
+        let sitemap_seeds = if task.discover_sitemaps {
+            task.seed_urls.clone()
+        } else {
+            vec![]
+        };
+
+        let guardrails = Guardrails::new(config.safety, &config.user_agent)?;
+        Ok(Self {
+            browser,
+            // Offline replays never touch the network, DNS included.
+            resolve_hosts: guardrails.needs_addresses() && !config.cache.offline,
+            resolved: HashMap::new(),
+            guardrails,
+            planner: Planner::new(task, config.dedup, config.frontier),
+            redactor: Redactor::new(&config.redaction),
+            task_name,
+            sitemap_seeds,
+            max_concurrent_requests: config.max_concurrent_requests.max(1),
+            retry: config.retry,
+            deferred: VecDeque::new(),
+            in_flight: Vec::new(),
+            state_path: None,
+            resumed: false,
+            run_id: format!("{:016x}", rand::random::<u64>()),
+            errors: Vec::new(),
+            pages: Vec::new(),
+            links: Vec::new(),
+            on_event: None,
+        })
+    }
+
+    /// Rank frontier URLs with a custom scorer. Only used by the
+    /// best-first strategy.
+    pub fn with_scorer(mut self, scorer: impl Scorer + 'static) -> Self {
+        self.planner.set_scorer(Box::new(scorer));
+        self
+    }
+
+    /// Save crawl progress to `path` after every page and when the run ends.
+    ///
+    /// If `path` already holds the state of an earlier run of the same task,
+    /// the crawl resumes from it: visited pages are not fetched again,
+    /// collected results are kept and requests already made count against
+    /// the (possibly larger) request budget. Sitemap discovery is skipped
+    /// on resume since the saved frontier already includes it.
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            let mut state = CrawlState::load(&path)?;
+            if state.task_name != self.task_name {
+                return Err(AgentError::State(format!(
+                    "{} belongs to task '{}', not '{}'",
+                    path.display(),
+                    state.task_name,
+                    self.task_name
+                )));
+            }
+            self.planner.restore(&mut state);
+            self.guardrails.restore(&mut state);
+            self.errors = std::mem::take(&mut state.errors);
+            self.pages = std::mem::take(&mut state.pages);
+            self.links = std::mem::take(&mut state.links);
+            self.redactor.stats = std::mem::take(&mut state.redactions);
+            if !state.run_id.is_empty() {
+                self.run_id = std::mem::take(&mut state.run_id);
+            }
+            self.sitemap_seeds.clear();
+            self.resumed = true;
+        }
+        self.state_path = Some(path);
+        Ok(self)
+    }
+
+    /// Register a callback invoked for every progress event.
+    pub fn on_event(mut self, callback: impl FnMut(&AgentEvent) + Send + 'static) -> Self {
+        self.on_event = Some(Box::new(callback));
+        self
+    }
+
+    /// Stream progress events through a channel instead of a callback.
+    ///
+    /// Replaces any callback registered with `on_event`.
+    pub fn event_channel(self) -> (Self, mpsc::UnboundedReceiver<AgentEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let agent = self.on_event(move |event| {
+            // The receiver may have been dropped; the agent keeps running.
+            let _ = tx.send(event.clone());
+        });
+        (agent, rx)
+    }
+
+    /// Run the task until the frontier or the request budget is exhausted.
+    ///
+    /// Up to `max_concurrent_requests` fetches run at once, subject to the
+    /// per-host connection cap and rate limit enforced by the guardrails.
+    /// Transient failures are retried as configured in `RetryConfig`; each
+    /// retry passes the guardrails again and counts against the budget.
+    pub async fn run(mut self) -> Report {
+        let start = Instant::now();
+        let mut in_flight: JoinSet<FetchOutcome> = JoinSet::new();
+        let mut budget_exhausted = false;
+        self.guardrails.start();
+
+        if self.resumed {
+            self.emit(AgentEvent::Resumed {
+                pages_visited: self.planner.pages_visited,
+                frontier: self.planner.frontier_len(),
+            });
+        }
+
+        self.discover_sitemaps().await;
+        self.checkpoint();
+
+        loop {
+            while !budget_exhausted && in_flight.len() < self.max_concurrent_requests {
+                let Some(mut visit) = self.next_visit() else {
+                    break;
+                };
+                let (url, depth) = (visit.url.clone(), visit.depth);
+
+                if let Err(e) = self.guardrails.check_budget() {
+                    self.stop_for_budget(visit, e);
+                    budget_exhausted = true;
+                    break;
+                }
+
+                if let Err(e) = self.guardrails.check_depth(depth) {
+                    self.record_unfetched(&visit, Decision::DepthExceeded, e.to_string());
+                    self.emit(AgentEvent::DepthExceeded {
+                        url,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+
+                if let Some(form) = &visit.form {
+                    if let Err(e) = self.guardrails.check_form(form) {
+                        self.record_unfetched(&visit, Decision::Blocked, e.to_string());
+                        self.emit(AgentEvent::UrlBlocked {
+                            url,
+                            reason: e.to_string(),
+                        });
+                        continue;
+                    }
+                }
+
+                if let Err(e) = self.check_policy(&url).await {
+                    self.record_unfetched(&visit, Decision::Blocked, e.to_string());
+                    self.emit(AgentEvent::UrlBlocked {
+                        url,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+
+                // Without quota left for the robots.txt request, the page's
+                // own quota check below rejects it.
+                if let Some(robots_url) = self
+                    .guardrails
+                    .robots_url_needed(&url)
+                    .filter(|r| self.guardrails.domain_has_quota(r))
+                {
+                    let Some(robots) = self.fetch_robots(&robots_url).await else {
+                        self.deferred.push_back(visit);
+                        continue;
+                    };
+                    self.guardrails.store_robots(&url, robots);
+                    if let Err(e) = self.guardrails.check_budget() {
+                        self.stop_for_budget(visit, e);
+                        budget_exhausted = true;
+                        break;
+                    }
+                }
+
+                if let Err(e) = self.guardrails.check_robots(&url) {
+                    self.record_unfetched(&visit, Decision::Blocked, e.to_string());
+                    self.emit(AgentEvent::UrlBlocked {
+                        url,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+
+                if let Err(e) = self.guardrails.check_quota(&url, visit.attempts > 0) {
+                    self.record_unfetched(&visit, Decision::OverBudget, e.to_string());
+                    self.emit(AgentEvent::OverBudget {
+                        url,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+
+                let Some(delay) = self.guardrails.reserve_host_slot(&url) else {
+                    self.deferred.push_back(visit);
+                    continue;
+                };
+
+                // Count the request when it is scheduled so concurrent
+                // fetches cannot overrun the budget.
+                self.guardrails.record_request(&url);
+                if visit.attempts == 0 {
+                    self.guardrails.record_page(&url);
+                }
+                visit.attempts += 1;
+                match (&visit.form, visit.page) {
+                    (Some(form), _) => self.emit(AgentEvent::SubmittingForm {
+                        url,
+                        method: form.method,
+                        depth,
+                    }),
+                    (None, Some(page)) => {
+                        self.emit(AgentEvent::VisitingNextPage { url, depth, page })
+                    }
+                    (None, None) => self.emit(AgentEvent::Visiting { url, depth }),
+                }
+
+                self.in_flight.push(visit.clone());
+                let browser = self.browser.clone();
+                in_flight.spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let started = Instant::now();
+                    let result = match &visit.form {
+                        Some(form) => browser.submit(form).await,
+                        None => browser.fetch(&visit.url).await,
+                    };
+                    (visit, result, started.elapsed())
+                });
+            }
+
+            match in_flight.join_next().await {
+                Some(Ok((visit, result, elapsed))) => {
+                    self.guardrails.release_host(&visit.url);
+                    if let Ok(page) = &result {
+                        self.guardrails.record_bytes(page.body.len() as u64);
+                    }
+                    self.in_flight
+                        .retain(|v| v.url != visit.url || v.form != visit.form);
+                    match failure(&result) {
+                        Some((kind, message, retry_after)) => {
+                            // A failed POST may still have reached the
+                            // server, so it is never sent a second time.
+                            let delay = match &visit.form {
+                                Some(form) if form.method == FormMethod::Post => None,
+                                _ => self.retry.delay(&kind, visit.attempts, retry_after),
+                            };
+                            match delay {
+                                Some(delay) => self.schedule_retry(visit, delay, message),
+                                None => {
+                                    self.handle_failure(visit, result, elapsed, kind, message)
+                                }
+                            }
+                        }
+                        None => self.handle_fetch(visit, result, elapsed),
+                    }
+                    self.checkpoint();
+                }
+                // A fetch task panicked; its host slot stays reserved,
+                // which only makes the agent more conservative.
+                Some(Err(_)) => {}
+                // Nothing in flight; wait for the next retry to fall due.
+                None => match self.next_retry_at() {
+                    Some(at) if !budget_exhausted && at > Instant::now() => {
+                        tokio::time::sleep_until(at).await;
+                    }
+                    _ => break,
+                },
+            }
+        }
+
+        if !budget_exhausted {
+            self.emit(AgentEvent::FrontierExhausted);
+        }
+        self.checkpoint();
+
+        Report {
+            task_name: self.task_name,
+            run_id: self.run_id,
+            items: self.planner.results,
+            pages_visited: self.planner.pages_visited,
+            pages_skipped: self.planner.pages_skipped,
+            budget_exhausted: self.guardrails.exhausted_budget(),
+            bytes_downloaded: self.guardrails.usage().bytes_downloaded,
+            safety_blocks: self.guardrails.safety_blocks,
+            policy_denials: self.guardrails.policy_denials,
+            redactions: self.redactor.stats,
+            errors: self.errors,
+            pages: self.pages,
+            links: self.links,
+            duration: start.elapsed(),
+        }
+    }
+
+    /// Stop scheduling because a budget ran out. The planner already
+    /// counts `visit` as visited; it is kept pending so the checkpoint
+    /// saves it for a resumed crawl.
+    fn stop_for_budget(&mut self, visit: Visit, error: AgentError) {
+        self.deferred.push_front(visit);
+        self.emit(AgentEvent::BudgetExhausted {
+            reason: error.to_string(),
+        });
+    }
+
+    /// Next URL to visit: deferred visits whose host has freed up and
+    /// which are not waiting out a retry delay first, then the planner's
+    /// frontier.
+    fn next_visit(&mut self) -> Option<Visit> {
+        let now = Instant::now();
+        if let Some(pos) = self.deferred.iter().position(|v| {
+            v.not_before.is_none_or(|at| at <= now) && self.guardrails.host_has_capacity(&v.url)
+        }) {
+            return self.deferred.remove(pos);
+        }
+
+        match self.planner.next_action() {
+            AgentAction::Visit(url, depth) => Some(Visit {
+                url,
+                depth,
+                form: None,
+                page: None,
+                attempts: 0,
+                not_before: None,
+            }),
+            AgentAction::Submit(form, depth) => Some(Visit {
+                url: form.url.clone(),
+                depth,
+                form: Some(form),
+                page: None,
+                attempts: 0,
+                not_before: None,
+            }),
+            AgentAction::NextPage(url, depth, page) => Some(Visit {
+                url,
+                depth,
+                form: None,
+                page: Some(page),
+                attempts: 0,
+                not_before: None,
+            }),
+            AgentAction::Done => None,
+        }
+    }
+
+    /// When the earliest pending retry falls due.
+    fn next_retry_at(&self) -> Option<Instant> {
+        self.deferred.iter().filter_map(|v| v.not_before).min()
+    }
+
+    fn schedule_retry(&mut self, mut visit: Visit, delay: Duration, reason: String) {
+        self.emit(AgentEvent::Retrying {
+            url: visit.url.clone(),
+            attempt: visit.attempts,
+            delay,
+            reason,
+        });
+        visit.not_before = Some(Instant::now() + delay);
+        self.deferred.push_back(visit);
+    }
+
+    /// Record a fetch that failed for good, then report it as usual.
+    fn handle_failure(
+        &mut self,
+        visit: Visit,
+        result: Result<PageContent>,
+        elapsed: Duration,
+        kind: FetchErrorKind,
+        message: String,
+    ) {
+        self.errors.push(FetchError {
+            url: visit.url.to_string(),
+            kind,
+            message,
+            attempts: visit.attempts,
+        });
+        self.handle_fetch(visit, result, elapsed);
+    }
+
+    /// A new audit record for `visit`.
+    fn page_record(&self, visit: &Visit, decision: Decision) -> PageRecord {
+        PageRecord {
+            url: visit.url.to_string(),
+            referrer: self.planner.referrer(&visit.url).map(Url::to_string),
+            depth: visit.depth,
+            decision,
+            reason: None,
+            status: None,
+            fetch_ms: None,
+            bytes: None,
+            title: None,
+            items: 0,
+            attempts: visit.attempts,
+        }
+    }
+
+    /// Record a visit the guardrails stopped before it was fetched.
+    fn record_unfetched(&mut self, visit: &Visit, decision: Decision, reason: String) {
+        let mut record = self.page_record(visit, decision);
+        record.reason = Some(reason);
+        self.record(record);
+    }
+
+    fn record(&mut self, record: PageRecord) {
+        self.emit(AgentEvent::PageRecorded {
+            record: record.clone(),
+        });
+        self.pages.push(record);
+    }
+
+    /// Check a URL against the URL policy, resolving its host first if the
+    /// policy needs its addresses.
+    async fn check_policy(&mut self, url: &Url) -> Result<()> {
+        let addrs = self.addresses(url).await;
+        let decision = self.guardrails.check_url(url, &addrs);
+        let denied = (decision.action == RuleAction::Deny)
+            .then(|| AgentError::SafetyViolation(decision.reason.clone()));
+        self.emit(AgentEvent::PolicyDecided { decision });
+        denied.map_or(Ok(()), Err)
+    }
+
+    /// The addresses `url`'s host resolves to, if the policy needs them.
+    /// A host that cannot be resolved has none; its fetch fails anyway.
+    async fn addresses(&mut self, url: &Url) -> Vec<IpAddr> {
+        let Some(Host::Domain(host)) = url.host() else {
+            return vec![];
+        };
+        if !self.resolve_hosts {
+            return vec![];
+        }
+        if let Some(addrs) = self.resolved.get(host) {
+            return addrs.clone();
+        }
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<IpAddr> = match tokio::net::lookup_host((host, port)).await {
+            Ok(addrs) => addrs.map(|addr| addr.ip()).collect(),
+            Err(_) => vec![],
+        };
+        self.resolved.insert(host.to_string(), addrs.clone());
+        addrs
+    }
+
+    /// Seed the frontier from the sitemaps of the seed URLs' sites.
+    ///
+    /// Sitemaps are taken from robots.txt `Sitemap:` lines, falling back to
+    /// `/sitemap.xml`. Sitemap indexes are followed, each sitemap fetch
+    /// counts against the request budget, and no more page URLs are queued
+    /// than the remaining budget could visit. Listed pages are treated as
+    /// additional seeds (depth 0).
+    async fn discover_sitemaps(&mut self) {
+        let mut queue: VecDeque<Url> = VecDeque::new();
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for seed in std::mem::take(&mut self.sitemap_seeds) {
+            if let Some(robots_url) = self.guardrails.robots_url_needed(&seed) {
+                // A denied robots.txt is not fetched; `/sitemap.xml` is
+                // then tried, and checked against the policy in turn.
+                let allowed = match self.check_policy(&robots_url).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        self.emit(AgentEvent::UrlBlocked {
+                            url: robots_url.clone(),
+                            reason: e.to_string(),
+                        });
+                        false
+                    }
+                };
+                if allowed
+                    && self.guardrails.check_budget().is_ok()
+                    && self.guardrails.domain_has_quota(&robots_url)
+                {
+                    if let Some(robots) = self.fetch_robots(&robots_url).await {
+                        self.guardrails.store_robots(&seed, robots);
+                    }
+                }
+            }
+
+            let mut candidates: Vec<Url> = self
+                .guardrails
+                .robots_for(&seed)
+                .map(|r| r.sitemaps.iter().filter_map(|s| seed.join(s).ok()).collect())
+                .unwrap_or_default();
+            if candidates.is_empty() {
+                candidates.extend(seed.join("/sitemap.xml").ok());
+            }
+
+            for url in candidates {
+                if seen.insert(url.to_string()) {
+                    queue.push_back(url);
+                }
+            }
+        }
+
+        let mut added = 0;
+        while let Some(sitemap_url) = queue.pop_front() {
+            if self.guardrails.check_budget().is_err() {
+                break;
+            }
+
+            if let Err(e) = self
+                .check_policy(&sitemap_url)
+                .await
+                .and_then(|_| self.guardrails.check_robots(&sitemap_url))
+                .and_then(|_| self.guardrails.check_quota(&sitemap_url, false))
+            {
+                self.emit(AgentEvent::UrlBlocked {
+                    url: sitemap_url,
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+
+            let Some(delay) = self.guardrails.reserve_host_slot(&sitemap_url) else {
+                continue;
+            };
+            tokio::time::sleep(delay).await;
+            let result = self.browser.fetch_bytes(&sitemap_url).await;
+            self.guardrails.release_host(&sitemap_url);
+            self.guardrails.record_request(&sitemap_url);
+            self.guardrails.record_page(&sitemap_url);
+            if let Ok((_, body)) = &result {
+                self.guardrails.record_bytes(body.len() as u64);
+            }
+
+            let body = match result {
+                Ok((200, body)) => body,
+                Ok((status, _)) => {
+                    self.emit(AgentEvent::HttpStatus {
+                        url: sitemap_url,
+                        status,
+                    });
+                    continue;
+                }
+                Err(e) => {
+                    self.emit(AgentEvent::FetchFailed {
+                        url: sitemap_url,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            match sitemap::parse(&body) {
+                Ok(Sitemap::Index(locs)) => {
+                    for url in locs.iter().filter_map(|l| sitemap_url.join(l).ok()) {
+                        if seen.insert(url.to_string()) {
+                            queue.push_back(url);
+                        }
+                    }
+                }
+                Ok(Sitemap::UrlSet(locs)) => {
+                    let mut urls_added = 0;
+                    for url in locs.iter().filter_map(|l| sitemap_url.join(l).ok()) {
+                        if added >= self.guardrails.requests_remaining() {
+                            break;
+                        }
+                        if self.planner.add_discovered(url, 0) {
+                            added += 1;
+                            urls_added += 1;
+                        }
+                    }
+                    self.emit(AgentEvent::SitemapProcessed {
+                        url: sitemap_url,
+                        urls_added,
+                    });
+                }
+                Err(e) => {
+                    self.emit(AgentEvent::FetchFailed {
+                        url: sitemap_url,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Fetch and parse a site's robots.txt. The request waits its turn on
+    /// the host and counts against the budget like a page fetch; `None` if
+    /// the host is at its connection cap.
+    ///
+    /// A missing file (4xx) allows everything; server errors and
+    /// unreachable hosts are treated as a complete disallow (RFC 9309).
+    async fn fetch_robots(&mut self, robots_url: &Url) -> Option<RobotsTxt> {
+        let delay = self.guardrails.reserve_host_slot(robots_url)?;
+        self.guardrails.record_request(robots_url);
+        tokio::time::sleep(delay).await;
+        let result = self.browser.fetch_bytes(robots_url).await;
+        self.guardrails.release_host(robots_url);
+        if let Ok((_, body)) = &result {
+            self.guardrails.record_bytes(body.len() as u64);
+        }
+
+        Some(match result {
+            Ok((200, body)) => RobotsTxt::parse(&String::from_utf8_lossy(&body)),
+            Ok((status, _)) if (400..500).contains(&status) => RobotsTxt::allow_all(),
+            _ => RobotsTxt::disallow_all(),
+        })
+    }
+
+    fn handle_fetch(&mut self, visit: Visit, result: Result<PageContent>, elapsed: Duration) {
+        let mut record = self.page_record(&visit, Decision::Processed);
+        record.fetch_ms = Some(elapsed.as_millis() as u64);
+        if let Err((decision, reason)) = self.process_fetch(visit, result, &mut record) {
+            record.decision = decision;
+            record.reason = Some(reason);
+        }
+        self.record(record);
+    }
+
+    /// Process a fetched page, filling in its audit record. Returns the
+    /// decision and reason if the page was not processed.
+    fn process_fetch(
+        &mut self,
+        visit: Visit,
+        result: Result<PageContent>,
+        record: &mut PageRecord,
+    ) -> std::result::Result<(), (Decision, String)> {
+        let url = visit.url;
+        let page = match result {
+            Ok(p) => p,
+            Err(AgentError::Content(reason)) => {
+                self.planner.pages_skipped += 1;
+                self.emit(AgentEvent::ContentSkipped {
+                    url,
+                    reason: reason.clone(),
+                });
+                return Err((Decision::ContentSkipped, reason));
+            }
+            Err(e) => {
+                self.emit(AgentEvent::FetchFailed {
+                    url,
+                    error: e.to_string(),
+                });
+                return Err((Decision::FetchFailed, e.to_string()));
+            }
+        };
+        record.status = Some(page.status);
+        record.bytes = Some(page.body.len());
+
+        if let Err(e) = self.guardrails.check_content(&page.body) {
+            self.emit(AgentEvent::ContentBlocked {
+                url,
+                reason: e.to_string(),
+            });
+            return Err((Decision::ContentBlocked, e.to_string()));
+        }
+
+        if page.status != 200 {
+            self.emit(AgentEvent::HttpStatus {
+                url,
+                status: page.status,
+            });
+            return Err((Decision::HttpError, format!("HTTP {}", page.status)));
+        }
+
+        let parsed = Browser::parse(&page);
+        record.title = parsed.title.clone();
+        if let Some(duplicate_of) = self.planner.check_duplicate(&parsed) {
+            let reason = format!("near-duplicate of {}", duplicate_of);
+            self.emit(AgentEvent::NearDuplicate {
+                url: parsed.url,
+                duplicate_of,
+            });
+            return Err((Decision::NearDuplicate, reason));
+        }
+        let before = self.planner.results.len();
+        self.planner.process_page(&parsed, visit.depth);
+        let extracted = self.planner.results.split_off(before);
+        let kept = self.redactor.redact(extracted);
+        self.planner.results.extend(kept);
+        record.items = self.planner.results.len() - before;
+
+        let mut seen = HashSet::new();
+        for link in &parsed.links {
+            if seen.insert(link.as_str()) {
+                self.links.push(Link {
+                    from: record.url.clone(),
+                    to: link.to_string(),
+                });
+            }
+        }
+
+        self.emit(AgentEvent::PageProcessed {
+            url: parsed.url.clone(),
+            title: parsed.title.clone(),
+            links: parsed.links.len(),
+            items: self.planner.results[before..].to_vec(),
+            items_total: self.planner.results.len(),
+        });
+        Ok(())
+    }
+
+    /// Save progress to the state file, if one is configured.
+    fn checkpoint(&mut self) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+
+        // Form submissions are not part of the saved state; ones still
+        // pending are not repeated by a resumed crawl.
+        let pending: Vec<(Url, usize)> = self
+            .in_flight
+            .iter()
+            .chain(&self.deferred)
+            .filter(|v| v.form.is_none())
+            .map(|v| (v.url.clone(), v.depth))
+            .collect();
+        let mut state = CrawlState {
+            task_name: self.task_name.clone(),
+            run_id: self.run_id.clone(),
+            requests_made: self.guardrails.requests_made(),
+            usage: self.guardrails.usage().clone(),
+            safety_blocks: self.guardrails.safety_blocks.clone(),
+            policy_denials: self.guardrails.policy_denials.clone(),
+            redactions: self.redactor.stats.clone(),
+            errors: self.errors.clone(),
+            pages: self.pages.clone(),
+            links: self.links.clone(),
+            ..Default::default()
+        };
+        self.planner.save_to(&mut state, &pending);
+
+        if let Err(e) = state.save(path) {
+            self.emit(AgentEvent::CheckpointFailed {
+                error: e.to_string(),
+            });
+        }
+    }
+
+    fn emit(&mut self, event: AgentEvent) {
+        if let Some(callback) = self.on_event.as_mut() {
+            callback(&event);
+        }
+    }
 }
 
-impl AutonomousAgent {
-    pub fn new() -> Self {
-        Self {
-            browser: Arc::new(WebBrowser::new()),
-            extractor: Arc::new(InformationExtractor::new()),
-            safety: Arc::new(SafetyGuardrails::new()),
+/// Why a fetch failed, if it did: the error kind, a message and the
+/// server's `Retry-After` delay.
+fn failure(result: &Result<PageContent>) -> Option<(FetchErrorKind, String, Option<Duration>)> {
+    match result {
+        Ok(page) if page.status == 200 => None,
+        // Skipped content is neither retried nor recorded as an error.
+        Err(AgentError::Content(_)) => None,
+        Ok(page) => Some((
+            FetchErrorKind::HttpStatus {
+                status: page.status,
+            },
+            format!("HTTP {}", page.status),
+            page.retry_after,
+        )),
+        Err(e) => Some((FetchErrorKind::classify(e), e.to_string(), None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CacheEntry, HttpCache};
+    use crate::config::{CacheConfig, PolicyConfig, SafetyConfig};
+    use crate::guardrails::Budget;
+    use crate::task::{ExtractionGoal, FormSubmission};
+    use reqwest::header::HeaderMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Record responses into a fresh cache directory.
+    fn record(name: &str, pages: &[(&str, u16, &str)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("agent-replay-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = HttpCache::open(&dir).unwrap();
+        for (url, status, body) in pages {
+            let url = Url::parse(url).unwrap();
+            let entry = CacheEntry::from_response(&url, &url, *status, &HeaderMap::new()).unwrap();
+            cache.store(&entry, body.as_bytes()).unwrap();
         }
+        dir
     }
 
-    pub async fn execute_task(&self, task: Task) -> Result<String> {
-        // Check safety guardrails
-        self.safety.check_task(&task)?;
+    fn task(seed: &str) -> Task {
+        Task {
+            name: "replay".to_string(),
+            seed_urls: vec![Url::parse(seed).unwrap()],
+            goal: ExtractionGoal::ExtractText {
+                selectors: vec!["h1".to_string()],
+            },
+            link_follow_patterns: vec![],
+            link_exclude_patterns: vec![],
+            discover_sitemaps: false,
+            forms: vec![],
+            pagination: None,
+        }
+    }
 
-        // Browse to the URL
-        let html = self.browser.fetch_page(&task.url).await?;
+    #[tokio::test]
+    async fn test_offline_replay_is_deterministic() {
+        let dir = record(
+            "crawl",
+            &[
+                ("https://shop.test/robots.txt", 200, "User-agent: *\nDisallow: /private\n"),
+                ("https://shop.test/", 200, r#"<h1>Home</h1><a href="/a">A</a> <a href="/private">P</a> <a href="/missing">M</a>"#),
+                ("https://shop.test/a", 200, r#"<h1>Page A</h1><a href="/">home</a>"#),
+            ],
+        );
+        let config = AgentConfig {
+            safety: SafetyConfig {
+                rate_limit_ms: 0,
+                ..Default::default()
+            },
+            cache: CacheConfig {
+                dir: Some(dir.clone()),
+                offline: true,
+            },
+            ..Default::default()
+        };
 
-        // Extract information based on task
-        let extracted_info = self.extractor.extract(&html, &task.query)?;
+        let (agent, mut events) = Agent::new(task("https://shop.test/"), config)
+            .unwrap()
+            .event_channel();
+        let report = agent.run().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Responses that were never recorded fail instead of going online.
+        let mut failed = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let AgentEvent::FetchFailed { url, .. } = event {
+                failed.push(url.to_string());
+            }
+        }
+        assert_eq!(failed, vec!["https://shop.test/missing"]);
 
-        // Perform autonomous actions if needed
-        // This could include following links, filling forms, etc.
+        let headings: Vec<String> = report.items.iter().map(|i| i.value_text()).collect();
+        assert_eq!(headings, vec!["Home", "Page A"]);
+        assert_eq!(report.pages_visited, 2);
+        assert!(report.safety_blocks.iter().any(|b| b.contains("/private")));
+    }
 
-        Ok(extracted_info)
+    /// Serve a page with a POST form on a local port, answering every POST
+    /// with a 503. Returns the seed URL and a counter of POSTs received.
+    async fn serve_failing_form() -> (Url, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let seed = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let posts = Arc::new(AtomicUsize::new(0));
+        let counter = posts.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let n = stream.read(&mut request).await.unwrap_or(0);
+                let response = if request[..n].starts_with(b"POST") {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_string()
+                } else {
+                    let body = r#"<h1>Home</h1><form method="post" action="/order"><input name="q" value="1"></form>"#;
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (seed, posts)
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_failed_post_is_not_retried() {
+        let (seed, posts) = serve_failing_form().await;
+        let config = AgentConfig {
+            safety: SafetyConfig {
+                rate_limit_ms: 0,
+                robots_exempt_domains: vec!["127.0.0.1".to_string()],
+                allowed_form_actions: vec!["/order$".to_string()],
+                policy: PolicyConfig {
+                    allowed_private_ranges: vec!["127.0.0.1/32".to_string()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            retry: RetryConfig {
+                base_delay_ms: 1,
+                jitter: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut task = task(seed.as_str());
+        task.forms = vec![FormSubmission {
+            selector: "form".to_string(),
+            page_pattern: None,
+            values: HashMap::new(),
+        }];
+
+        let report = Agent::new(task, config).unwrap().run().await;
+
+        assert_eq!(posts.load(Ordering::SeqCst), 1);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].kind, FetchErrorKind::HttpStatus { status: 503 });
+        assert_eq!(report.errors[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_discovery_checks_policy_first() {
+        let dir = record(
+            "sitemap-policy",
+            &[
+                ("http://127.0.0.1/robots.txt", 200, "Sitemap: http://127.0.0.1/map.xml\n"),
+                ("http://127.0.0.1/sitemap.xml", 200, "<urlset></urlset>"),
+            ],
+        );
+        let config = AgentConfig {
+            cache: CacheConfig {
+                dir: Some(dir.clone()),
+                offline: true,
+            },
+            ..Default::default()
+        };
+        let mut task = task("http://127.0.0.1/");
+        task.discover_sitemaps = true;
+
+        let (agent, mut events) = Agent::new(task, config).unwrap().event_channel();
+        let report = agent.run().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut blocked = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let AgentEvent::UrlBlocked { url, .. } = event {
+                blocked.push(url.to_string());
+            }
+        }
+        assert_eq!(
+            blocked,
+            vec![
+                "http://127.0.0.1/robots.txt",
+                "http://127.0.0.1/sitemap.xml",
+                "http://127.0.0.1/",
+            ]
+        );
+        assert_eq!(report.bytes_downloaded, 0);
+    }
+
+    #[tokio::test]
+    async fn test_robots_txt_counts_against_budget() {
+        let robots = "User-agent: *\n";
+        let dir = record(
+            "robots",
+            &[
+                ("https://shop.test/robots.txt", 200, robots),
+                ("https://shop.test/", 200, r#"<h1>Home</h1><a href="/a">A</a>"#),
+                ("https://shop.test/a", 200, "<h1>A</h1>"),
+            ],
+        );
+        let config = AgentConfig {
+            safety: SafetyConfig {
+                rate_limit_ms: 0,
+                max_requests: 2,
+                ..Default::default()
+            },
+            cache: CacheConfig {
+                dir: Some(dir.clone()),
+                offline: true,
+            },
+            ..Default::default()
+        };
+
+        let report = Agent::new(task("https://shop.test/"), config).unwrap().run().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.pages_visited, 1);
+        assert_eq!(report.budget_exhausted, Some(Budget::Requests));
+        let home = r#"<h1>Home</h1><a href="/a">A</a>"#;
+        assert_eq!(report.bytes_downloaded, (robots.len() + home.len()) as u64);
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_budget_visits_every_page() {
+        let dir = record(
+            "budget",
+            &[
+                ("https://shop.test/robots.txt", 200, "User-agent: *\n"),
+                ("https://shop.test/", 200, r#"<h1>Home</h1><a href="/a">A</a> <a href="/b">B</a> <a href="/c">C</a>"#),
+                ("https://shop.test/a", 200, "<h1>A</h1>"),
+                ("https://shop.test/b", 200, "<h1>B</h1>"),
+                ("https://shop.test/c", 200, "<h1>C</h1>"),
+            ],
+        );
+        let state = dir.join("state.json");
+        let config = |max_requests| AgentConfig {
+            safety: SafetyConfig {
+                rate_limit_ms: 0,
+                max_requests,
+                ..Default::default()
+            },
+            cache: CacheConfig {
+                dir: Some(dir.clone()),
+                offline: true,
+            },
+            ..Default::default()
+        };
+
+        let agent = Agent::new(task("https://shop.test/"), config(3))
+            .unwrap()
+            .with_state_file(&state)
+            .unwrap();
+        let report = agent.run().await;
+        assert_eq!(report.budget_exhausted, Some(Budget::Requests));
+        assert!(report.pages_visited < 4);
+
+        let agent = Agent::new(task("https://shop.test/"), config(100))
+            .unwrap()
+            .with_state_file(&state)
+            .unwrap();
+        let report = agent.run().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut headings: Vec<String> = report.items.iter().map(|i| i.value_text()).collect();
+        headings.sort();
+        assert_eq!(headings, vec!["A", "B", "C", "Home"]);
+        assert_eq!(report.pages_visited, 4);
+    }
+}
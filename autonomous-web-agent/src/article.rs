@@ -0,0 +1,381 @@
+use crate::transform;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Elements that never hold article text.
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "iframe", "nav", "aside", "header", "footer",
+    "form", "button", "select",
+];
+
+/// Elements whose text starts a new paragraph.
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ul",
+    "ol",
+    "pre",
+    "blockquote",
+    "table",
+    "tr",
+    "figure",
+    "figcaption",
+    "dl",
+    "dt",
+    "dd",
+    "br",
+];
+
+/// Class and id words of boilerplate containers, and of content ones.
+const NEGATIVE: &str = r"(?i)comment|footer|nav|sidebar|menu|share|social|related|promo|advert|\bads?\b|banner|cookie|popup|newsletter|breadcrumb|widget|masthead|subscribe";
+const POSITIVE: &str = r"(?i)article|body|content|entry|main|post|story|text|blog";
+
+/// Paragraphs shorter than this do not vote for their container.
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// The main content of a page, with its metadata.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Article {
+    pub title: Option<String>,
+    pub byline: Option<String>,
+    /// Publish date, normalized to ISO 8601 when it can be parsed.
+    pub published: Option<String>,
+    /// Paragraphs of the article, separated by blank lines.
+    pub text: String,
+}
+
+/// Extract a page's main content, leaving out navigation, sidebars,
+/// footers, scripts and other boilerplate. Returns `None` if the page has
+/// no block of prose to speak of.
+///
+/// As in Readability, every paragraph scores its parent and (at half
+/// weight) its grandparent by length and number of commas. Containers
+/// are weighted by their class and id and discounted by the share of
+/// their text that is link text; the best one, and any siblings scoring
+/// close to it, make up the article.
+pub fn extract(document: &Html) -> Option<Article> {
+    let negative = Regex::new(NEGATIVE).expect("valid pattern");
+    let positive = Regex::new(POSITIVE).expect("valid pattern");
+    let skipped = |el: &ElementRef| is_skipped(el, &negative, &positive);
+
+    let paragraphs = Selector::parse("p, pre, td, blockquote").expect("valid selector");
+    let mut scores: HashMap<_, f64> = HashMap::new();
+    let mut candidates: Vec<ElementRef> = Vec::new();
+    for paragraph in document.select(&paragraphs) {
+        if std::iter::successors(Some(paragraph), |el| el.parent().and_then(ElementRef::wrap))
+            .any(|el| skipped(&el))
+        {
+            continue;
+        }
+        let text = normalize(&paragraph.text().collect::<String>());
+        if text.chars().count() < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (text.len() as f64 / 100.0).min(3.0);
+
+        let parent = paragraph.parent().and_then(ElementRef::wrap);
+        let grandparent = parent.and_then(|p| p.parent()).and_then(ElementRef::wrap);
+        for (ancestor, share) in [(parent, 1.0), (grandparent, 0.5)] {
+            let Some(ancestor) = ancestor else { continue };
+            let entry = scores.entry(ancestor.id()).or_insert_with(|| {
+                candidates.push(ancestor);
+                class_weight(&ancestor, &negative, &positive)
+            });
+            *entry += score * share;
+        }
+    }
+
+    let final_score = |el: &ElementRef| scores[&el.id()] * (1.0 - link_density(el));
+    let top = candidates
+        .iter()
+        .copied()
+        .max_by(|a, b| final_score(a).total_cmp(&final_score(b)))?;
+    let threshold = (final_score(&top) * 0.2).max(10.0);
+
+    // Siblings that score well, or are substantial paragraphs, belong to
+    // the article too, e.g. when its text is split across several divs.
+    let parts: Vec<ElementRef> = match top.parent() {
+        Some(parent) => parent
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|el| {
+                el.id() == top.id()
+                    || scores
+                        .get(&el.id())
+                        .is_some_and(|_| final_score(el) >= threshold)
+                    || el.value().name() == "p"
+                        && normalize(&el.text().collect::<String>()).len() > 80
+                        && link_density(el) < 0.25
+            })
+            .collect(),
+        None => vec![top],
+    };
+
+    let mut paragraphs = Vec::new();
+    for part in parts {
+        let mut current = String::new();
+        render(part, &skipped, &mut paragraphs, &mut current);
+        flush(&mut paragraphs, &mut current);
+    }
+    let title = title(document);
+    // The heading usually repeats the title.
+    if paragraphs
+        .first()
+        .is_some_and(|p| Some(p) == title.as_ref())
+    {
+        paragraphs.remove(0);
+    }
+    if paragraphs.is_empty() {
+        return None;
+    }
+
+    Some(Article {
+        title,
+        byline: byline(document),
+        published: published(document),
+        text: paragraphs.join("\n\n"),
+    })
+}
+
+fn is_skipped(el: &ElementRef, negative: &Regex, positive: &Regex) -> bool {
+    let name = el.value().name();
+    if SKIPPED_TAGS.contains(&name) {
+        return true;
+    }
+    if matches!(name, "html" | "body" | "article" | "main") {
+        return false;
+    }
+    let names = format!(
+        "{} {}",
+        el.value().attr("class").unwrap_or(""),
+        el.value().id().unwrap_or("")
+    );
+    negative.is_match(&names) && !positive.is_match(&names)
+}
+
+fn class_weight(el: &ElementRef, negative: &Regex, positive: &Regex) -> f64 {
+    let mut weight = match el.value().name() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "td" | "blockquote" | "pre" => 3.0,
+        _ => 0.0,
+    };
+    for name in [el.value().attr("class"), el.value().id()]
+        .into_iter()
+        .flatten()
+    {
+        if negative.is_match(name) {
+            weight -= 25.0;
+        }
+        if positive.is_match(name) {
+            weight += 25.0;
+        }
+    }
+    weight
+}
+
+/// The share of an element's text that is link text.
+fn link_density(el: &ElementRef) -> f64 {
+    let total: usize = el.text().map(str::len).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let anchors = Selector::parse("a").expect("valid selector");
+    let linked: usize = el
+        .select(&anchors)
+        .flat_map(|a| a.text())
+        .map(str::len)
+        .sum();
+    linked as f64 / total as f64
+}
+
+/// Append an element's text to `current`, starting a new paragraph at
+/// every block element and leaving out boilerplate and link lists.
+fn render(
+    el: ElementRef,
+    skipped: &dyn Fn(&ElementRef) -> bool,
+    paragraphs: &mut Vec<String>,
+    current: &mut String,
+) {
+    for child in el.children() {
+        match child.value() {
+            Node::Text(text) => current.push_str(text),
+            Node::Element(element) => {
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                if skipped(&child) {
+                    continue;
+                }
+                let block = BLOCK_TAGS.contains(&element.name());
+                if block && element.name() != "li" && link_density(&child) > 0.5 {
+                    continue;
+                }
+                if block {
+                    flush(paragraphs, current);
+                }
+                render(child, skipped, paragraphs, current);
+                if block {
+                    flush(paragraphs, current);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn flush(paragraphs: &mut Vec<String>, current: &mut String) {
+    let text = normalize(current);
+    if !text.is_empty() {
+        paragraphs.push(text);
+    }
+    current.clear();
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The first non-empty value found by `selectors`, each paired with the
+/// attribute to read (`None` for the element's text).
+fn first_value(document: &Html, selectors: &[(&str, Option<&str>)]) -> Option<String> {
+    selectors.iter().find_map(|(selector, attribute)| {
+        let selector = Selector::parse(selector).ok()?;
+        document.select(&selector).find_map(|el| {
+            let value = match attribute {
+                Some(attribute) => el.value().attr(attribute)?.to_string(),
+                None => el.text().collect(),
+            };
+            Some(normalize(&value)).filter(|v| !v.is_empty())
+        })
+    })
+}
+
+fn title(document: &Html) -> Option<String> {
+    first_value(
+        document,
+        &[
+            ("meta[property='og:title']", Some("content")),
+            ("h1", None),
+            ("title", None),
+        ],
+    )
+}
+
+fn byline(document: &Html) -> Option<String> {
+    let byline = first_value(
+        document,
+        &[
+            ("meta[name='author']", Some("content")),
+            ("[itemprop='author'] [itemprop='name']", None),
+            ("[itemprop='author']", None),
+            ("[rel='author']", None),
+            (".byline", None),
+            (".author", None),
+        ],
+    )?;
+    let byline = match byline.get(..3) {
+        Some(by) if by.eq_ignore_ascii_case("by ") => byline[3..].to_string(),
+        _ => byline,
+    };
+    (byline.len() <= 100).then_some(byline)
+}
+
+fn published(document: &Html) -> Option<String> {
+    let date = first_value(
+        document,
+        &[
+            ("meta[property='article:published_time']", Some("content")),
+            ("meta[itemprop='datePublished']", Some("content")),
+            ("[itemprop='datePublished']", Some("datetime")),
+            ("meta[name='date']", Some("content")),
+            ("meta[name='publish-date']", Some("content")),
+            ("time[datetime]", Some("datetime")),
+        ],
+    )?;
+    Some(transform::parse_date(&date, None).unwrap_or(date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head>
+        <title>Rust 2.0 announced | Example News</title>
+        <meta name="author" content="By Jane Doe">
+        <meta property="article:published_time" content="2024-03-05T09:30:00+01:00">
+        <script>var tracking = "should not appear";</script>
+        <style>.x { color: red }</style>
+      </head><body>
+        <nav><a href="/">Home</a> <a href="/news">News</a> <a href="/about">About</a></nav>
+        <div class="layout">
+          <div id="sidebar"><p>Subscribe to our newsletter for the latest news, offers and more.</p></div>
+          <div class="post-content">
+            <h1>Rust 2.0 announced</h1>
+            <p>The Rust team announced a new major version today, the first since 2015, with a long list of changes.</p>
+            <p>Among them are faster compile times, a new borrow checker, and improved async support, according to the release notes.</p>
+            <ul class="share"><li><a href="/tw">Tweet this article now</a></li><li><a href="/fb">Share on Facebook</a></li></ul>
+            <p>Upgrading existing code should be mostly automatic, the team said.</p>
+          </div>
+        </div>
+        <footer><p>Copyright 2024 Example News, all rights reserved, do not copy.</p></footer>
+      </body></html>"#;
+
+    #[test]
+    fn test_extracts_main_content() {
+        let article = extract(&Html::parse_document(PAGE)).unwrap();
+        assert_eq!(article.title.as_deref(), Some("Rust 2.0 announced"));
+        assert_eq!(article.byline.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            article.published.as_deref(),
+            Some("2024-03-05T09:30:00+01:00")
+        );
+
+        let paragraphs: Vec<&str> = article.text.split("\n\n").collect();
+        assert_eq!(paragraphs.len(), 3, "{}", article.text);
+        assert!(paragraphs[0].starts_with("The Rust team announced"));
+        assert_eq!(
+            paragraphs[2],
+            "Upgrading existing code should be mostly automatic, the team said."
+        );
+        for boilerplate in [
+            "Home",
+            "newsletter",
+            "Tweet",
+            "Copyright",
+            "tracking",
+            "color",
+        ] {
+            assert!(!article.text.contains(boilerplate), "{}", boilerplate);
+        }
+    }
+
+    #[test]
+    fn test_metadata_fallbacks() {
+        let html = r#"<html><head><title>Fallback</title></head><body><article>
+            <p class="byline">by <a rel="author" href="/a">Sam Lee</a></p>
+            <time datetime="2023-11-02">Nov 2</time>
+            <p>Plenty of prose goes here, long enough to count as a paragraph of the article.</p>
+            </article></body></html>"#;
+        let article = extract(&Html::parse_document(html)).unwrap();
+        assert_eq!(article.title.as_deref(), Some("Fallback"));
+        assert_eq!(article.byline.as_deref(), Some("Sam Lee"));
+        assert_eq!(article.published.as_deref(), Some("2023-11-02"));
+
+        let empty =
+            Html::parse_document("<html><body><nav><a href='/'>Home</a></nav></body></html>");
+        assert_eq!(extract(&empty), None);
+    }
+}
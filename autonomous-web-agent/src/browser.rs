@@ -1,19 +1,43 @@
-use crate::config::AgentConfig;
-use crate::error::Result;
-use reqwest::Client;
+use crate::cache::{self, CacheEntry, CachedResponse, HttpCache};
+use crate::config::{AgentConfig, ContentConfig, ContentKind, RuleAction};
+use crate::content;
+use crate::error::{AgentError, Result};
+use crate::forms::{FormMethod, FormRequest};
+use crate::policy::Policy;
+use crate::retry;
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::{CONTENT_TYPE, IF_MODIFIED_SINCE, IF_NONE_MATCH, RETRY_AFTER};
+use reqwest::{redirect, Client, StatusCode};
 use scraper::{Html, Selector};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 use url::Url;
 
+/// Redirects followed before a request fails, as in reqwest's default
+/// policy.
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Clone)]
 pub struct Browser {
     client: Client,
+    cache: Option<HttpCache>,
+    offline: bool,
+    content: ContentConfig,
 }
 
 #[derive(Debug)]
 pub struct PageContent {
     pub url: Url,
     pub status: u16,
+    /// What the body holds, from the response's `Content-Type`.
+    pub kind: ContentKind,
+    /// The body, decoded according to its charset.
     pub body: String,
+    /// The server's `Retry-After` delay, if it sent one.
+    pub retry_after: Option<Duration>,
 }
 
 #[derive(Debug)]
@@ -21,33 +45,277 @@ pub struct ParsedPage {
     pub url: Url,
     pub title: Option<String>,
     pub links: Vec<Url>,
+    /// Anchor text of each link, keyed by URL. Texts of links to the same
+    /// URL are joined.
+    pub link_text: HashMap<String, String>,
     pub text_content: String,
+    pub kind: ContentKind,
+    /// The parsed HTML; empty for other kinds of content.
     pub document: Html,
 }
 
 impl Browser {
+    /// Create a browser. The URL policy is enforced on every redirect, and
+    /// non-public addresses are refused when host names are resolved, so
+    /// neither a redirect nor a DNS answer that changed since the agent
+    /// checked the URL can reach an internal service.
     pub fn new(config: &AgentConfig) -> Result<Self> {
+        let policy = Arc::new(Policy::new(&config.safety)?);
+        let redirect_policy = policy.clone();
         let client = Client::builder()
             .user_agent(&config.user_agent)
             .timeout(Duration::from_secs(config.request_timeout_secs))
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                let decision = redirect_policy.evaluate(attempt.url(), &[]);
+                match decision.action {
+                    RuleAction::Allow => attempt.follow(),
+                    RuleAction::Deny => {
+                        attempt.error(format!("redirect refused: {}", decision.reason))
+                    }
+                }
+            }))
+            .dns_resolver(Arc::new(PublicResolver { policy }))
             .build()?;
-        Ok(Self { client })
+
+        let cache = config.cache.dir.as_deref().map(HttpCache::open).transpose()?;
+        if config.cache.offline && cache.is_none() {
+            return Err(AgentError::Cache(
+                "offline mode requires a cache directory".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            client,
+            cache,
+            offline: config.cache.offline,
+            content: config.content.clone(),
+        })
     }
 
+    /// Fetch a page.
+    ///
+    /// Bodies over the size limit, and successful responses of a kind the
+    /// agent is not configured to process, fail with `AgentError::Content`
+    /// without being downloaded.
     pub async fn fetch(&self, url: &Url) -> Result<PageContent> {
-        let response = self.client.get(url.as_str()).send().await?;
+        let response = self.get(url, true).await?;
+        Ok(Self::page_content(response))
+    }
+
+    fn page_content(response: CachedResponse) -> PageContent {
+        let content_type = response.content_type.as_deref();
+        let kind = content::kind_of(content_type).unwrap_or(ContentKind::Html);
+        PageContent {
+            body: content::decode(&response.body, content_type, kind),
+            url: response.final_url,
+            status: response.status,
+            kind,
+            retry_after: response.retry_after,
+        }
+    }
+
+    /// GET a URL through the cache, if one is configured.
+    ///
+    /// Fresh entries are served directly; stale ones with an ETag or
+    /// Last-Modified are revalidated with a conditional request. In
+    /// offline mode every request is served from the cache. With `pages`
+    /// set, only content the agent processes is accepted.
+    async fn get(&self, url: &Url, pages: bool) -> Result<CachedResponse> {
+        let Some(cache) = &self.cache else {
+            let response = self.client.get(url.as_str()).send().await?;
+            return self
+                .read_response(url, response, pages)
+                .await
+                .map(|(response, _)| response);
+        };
+
+        let cached = cache.lookup(url);
+        if self.offline {
+            let (_, response) =
+                cached.ok_or_else(|| AgentError::Cache(format!("not in cache (offline): {}", url)))?;
+            return self.check_cached(response, pages);
+        }
+
+        let mut request = self.client.get(url.as_str());
+        match &cached {
+            Some((entry, response)) if entry.is_fresh(cache::now()) => {
+                return self.check_cached(response.clone(), pages)
+            }
+            Some((entry, _)) => {
+                if let Some(etag) = &entry.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &entry.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            None => {}
+        }
+
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some((mut entry, cached_response)) = cached.filter(|(e, _)| e.has_validators()) {
+                entry.revalidated(response.headers());
+                // A failed metadata update only costs a revalidation later.
+                let _ = cache.update(&entry);
+                return self.check_cached(cached_response, pages);
+            }
+        }
+
+        let (fresh, entry) = self.read_response(url, response, pages).await?;
+        if let Some(entry) = entry {
+            // Caching is best-effort; the crawl goes on without it.
+            let _ = cache.store(&entry, &fresh.body);
+        }
+        Ok(fresh)
+    }
+
+    /// Read a response body, checking its headers first and stopping once
+    /// it grows past the size limit.
+    async fn read_response(
+        &self,
+        url: &Url,
+        mut response: reqwest::Response,
+        pages: bool,
+    ) -> Result<(CachedResponse, Option<CacheEntry>)> {
         let final_url = response.url().clone();
         let status = response.status().as_u16();
-        let body = response.text().await?;
-
-        Ok(PageContent {
-            url: final_url,
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        self.check_response(
+            &final_url,
             status,
-            body,
-        })
+            content_type.as_deref(),
+            response.content_length(),
+            pages,
+        )?;
+
+        let entry = CacheEntry::from_response(url, &final_url, status, response.headers());
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(retry::parse_retry_after);
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (body.len() + chunk.len()) as u64 > self.content.max_body_bytes {
+                return Err(self.too_large(&final_url));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok((
+            CachedResponse {
+                final_url,
+                status,
+                content_type,
+                body,
+                retry_after,
+            },
+            entry,
+        ))
+    }
+
+    /// Apply `check_response` to a response served from the cache.
+    fn check_cached(&self, response: CachedResponse, pages: bool) -> Result<CachedResponse> {
+        self.check_response(
+            &response.final_url,
+            response.status,
+            response.content_type.as_deref(),
+            Some(response.body.len() as u64),
+            pages,
+        )?;
+        Ok(response)
+    }
+
+    /// Refuse a response that is too large or, with `pages` set, a
+    /// successful response of a kind the agent does not process. Error
+    /// pages are let through whatever their type; they are never parsed.
+    fn check_response(
+        &self,
+        url: &Url,
+        status: u16,
+        content_type: Option<&str>,
+        length: Option<u64>,
+        pages: bool,
+    ) -> Result<()> {
+        if length.is_some_and(|length| length > self.content.max_body_bytes) {
+            return Err(self.too_large(url));
+        }
+        if pages && status == 200 {
+            match content::kind_of(content_type) {
+                Some(ContentKind::Html) => {}
+                Some(kind) if self.content.extract.contains(&kind) => {}
+                _ => {
+                    return Err(AgentError::Content(format!(
+                        "{} is {}",
+                        url,
+                        content_type.unwrap_or("of unknown type")
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn too_large(&self, url: &Url) -> AgentError {
+        AgentError::Content(format!(
+            "{} is larger than {} bytes",
+            url, self.content.max_body_bytes
+        ))
+    }
+
+    /// Submit a filled-in form: a GET to its URL (through the cache), or a
+    /// form-encoded POST (never cached, and refused in offline mode).
+    pub async fn submit(&self, form: &FormRequest) -> Result<PageContent> {
+        if form.method == FormMethod::Get {
+            return self.fetch(&form.url).await;
+        }
+        if self.offline {
+            return Err(AgentError::Cache(format!(
+                "cannot POST in offline mode: {}",
+                form.url
+            )));
+        }
+
+        let response = self
+            .client
+            .post(form.url.as_str())
+            .form(&form.body)
+            .send()
+            .await?;
+        let (response, _) = self.read_response(&form.url, response, true).await?;
+        Ok(Self::page_content(response))
+    }
+
+    /// Fetch a URL as raw bytes, for non-HTML resources such as
+    /// (possibly gzip-compressed) sitemaps. Only the size limit applies.
+    pub async fn fetch_bytes(&self, url: &Url) -> Result<(u16, Vec<u8>)> {
+        let response = self.get(url, false).await?;
+        Ok((response.status, response.body))
     }
 
     pub fn parse(page: &PageContent) -> ParsedPage {
+        if page.kind != ContentKind::Html {
+            let document = content::read_document(page.kind, &page.body, &page.url);
+            return ParsedPage {
+                url: page.url.clone(),
+                title: document.title,
+                links: document.links,
+                link_text: HashMap::new(),
+                text_content: document.text,
+                kind: page.kind,
+                document: Html::new_document(),
+            };
+        }
+
         let document = Html::parse_document(&page.body);
 
         let title = Selector::parse("title")
@@ -55,7 +323,7 @@ impl Browser {
             .and_then(|sel| document.select(&sel).next())
             .map(|el| el.inner_html().trim().to_string());
 
-        let links = Self::extract_links(&document, &page.url);
+        let (links, link_text) = Self::extract_links(&document, &page.url);
 
         let text_content = document.root_element().text().collect::<Vec<_>>().join(" ");
 
@@ -63,24 +331,214 @@ impl Browser {
             url: page.url.clone(),
             title,
             links,
+            link_text,
             text_content,
+            kind: ContentKind::Html,
             document,
         }
     }
 
-    fn extract_links(document: &Html, base_url: &Url) -> Vec<Url> {
+    fn extract_links(document: &Html, base_url: &Url) -> (Vec<Url>, HashMap<String, String>) {
         let selector = match Selector::parse("a[href]") {
             Ok(s) => s,
-            Err(_) => return vec![],
+            Err(_) => return (vec![], HashMap::new()),
         };
 
-        document
-            .select(&selector)
-            .filter_map(|el| {
-                let href = el.value().attr("href")?;
-                base_url.join(href).ok()
-            })
-            .filter(|url| matches!(url.scheme(), "http" | "https"))
-            .collect()
+        let mut links = Vec::new();
+        let mut link_text: HashMap<String, String> = HashMap::new();
+        for el in document.select(&selector) {
+            let Some(url) = el.value().attr("href").and_then(|href| base_url.join(href).ok()) else {
+                continue;
+            };
+            if !matches!(url.scheme(), "http" | "https") {
+                continue;
+            }
+
+            let text = el.text().collect::<Vec<_>>().join(" ");
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() {
+                let entry = link_text.entry(url.to_string()).or_default();
+                if !entry.is_empty() {
+                    entry.push(' ');
+                }
+                entry.push_str(&text);
+            }
+            links.push(url);
+        }
+        (links, link_text)
+    }
+}
+
+/// Resolves host names, dropping the addresses the policy does not allow
+/// the agent to connect to.
+struct PublicResolver {
+    policy: Arc<Policy>,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let resolved: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            let allowed: Vec<SocketAddr> = resolved
+                .iter()
+                .copied()
+                .filter(|addr| policy.allows_address(addr.ip()))
+                .collect();
+            if allowed.is_empty() && !resolved.is_empty() {
+                return Err(format!("{} resolves only to non-public addresses", name.as_str()).into());
+            }
+            let addrs: Addrs = Box::new(allowed.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CacheConfig;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    fn html(url: &str, body: &str) -> PageContent {
+        PageContent {
+            url: Url::parse(url).unwrap(),
+            status: 200,
+            kind: ContentKind::Html,
+            body: body.to_string(),
+            retry_after: None,
+        }
+    }
+
+    /// A browser replaying `pages` of `(url, content type, body)` from a
+    /// fresh cache directory.
+    fn offline(
+        name: &str,
+        pages: &[(&str, &str, &str)],
+        content: ContentConfig,
+    ) -> (Browser, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("browser-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = HttpCache::open(&dir).unwrap();
+        for (url, content_type, body) in pages {
+            let url = Url::parse(url).unwrap();
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type).unwrap());
+            let entry = CacheEntry::from_response(&url, &url, 200, &headers).unwrap();
+            cache.store(&entry, body.as_bytes()).unwrap();
+        }
+        let config = AgentConfig {
+            cache: CacheConfig {
+                dir: Some(dir.clone()),
+                offline: true,
+            },
+            content,
+            ..Default::default()
+        };
+        (Browser::new(&config).unwrap(), dir)
+    }
+
+    #[test]
+    fn test_parse_links_and_text() {
+        let page = Browser::parse(&html(
+            "https://example.com/docs/",
+            r#"<title> Docs </title><p>Intro</p>
+               <a href="a">Guide</a> <a href="/a#top">part two</a>
+               <a href="mailto:x@example.com">Mail</a> <a href="javascript:void(0)">JS</a>"#,
+        ));
+        assert_eq!(page.title.as_deref(), Some("Docs"));
+        let links: Vec<&str> = page.links.iter().map(Url::as_str).collect();
+        assert_eq!(
+            links,
+            ["https://example.com/docs/a", "https://example.com/a#top"]
+        );
+        assert_eq!(page.link_text["https://example.com/docs/a"], "Guide");
+        assert!(page.text_content.contains("Intro"));
+    }
+
+    #[test]
+    fn test_offline_mode_needs_a_cache() {
+        let config = AgentConfig {
+            cache: CacheConfig {
+                dir: None,
+                offline: true,
+            },
+            ..Default::default()
+        };
+        assert!(matches!(Browser::new(&config), Err(AgentError::Cache(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_checks_content_kind_and_size() {
+        let (browser, dir) = offline(
+            "kinds",
+            &[
+                (
+                    "https://example.com/",
+                    "text/html; charset=utf-8",
+                    "<h1>Home</h1>",
+                ),
+                (
+                    "https://example.com/data.json",
+                    "application/json",
+                    r#"{"a": 1}"#,
+                ),
+                (
+                    "https://example.com/report.pdf",
+                    "application/pdf",
+                    "%PDF-1.4",
+                ),
+                ("https://example.com/big", "text/html", &"x".repeat(100)),
+            ],
+            ContentConfig {
+                max_body_bytes: 50,
+                extract: vec![ContentKind::Json],
+            },
+        );
+        async fn fetch(browser: &Browser, url: &str) -> Result<PageContent> {
+            browser.fetch(&Url::parse(url).unwrap()).await
+        }
+
+        let page = fetch(&browser, "https://example.com/").await.unwrap();
+        assert_eq!((page.status, page.kind), (200, ContentKind::Html));
+        assert_eq!(page.body, "<h1>Home</h1>");
+        assert_eq!(
+            fetch(&browser, "https://example.com/data.json")
+                .await
+                .unwrap()
+                .kind,
+            ContentKind::Json
+        );
+        assert!(matches!(
+            fetch(&browser, "https://example.com/report.pdf").await,
+            Err(AgentError::Content(_))
+        ));
+        assert!(matches!(
+            fetch(&browser, "https://example.com/big").await,
+            Err(AgentError::Content(_))
+        ));
+        assert!(matches!(
+            fetch(&browser, "https://example.com/missing").await,
+            Err(AgentError::Cache(_))
+        ));
+
+        // Sitemaps and other raw fetches are not limited to page kinds.
+        let (status, body) = browser
+            .fetch_bytes(&Url::parse("https://example.com/report.pdf").unwrap())
+            .await
+            .unwrap();
+        assert_eq!((status, body.as_slice()), (200, b"%PDF-1.4".as_slice()));
+
+        let post = FormRequest {
+            method: FormMethod::Post,
+            url: Url::parse("https://example.com/").unwrap(),
+            body: vec![("q".to_string(), "rust".to_string())],
+        };
+        assert!(matches!(
+            browser.submit(&post).await,
+            Err(AgentError::Cache(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
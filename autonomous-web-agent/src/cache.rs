@@ -0,0 +1,248 @@
+use crate::error::{AgentError, Result};
+use reqwest::header::{HeaderMap, CACHE_CONTROL, CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// A response as stored in, or served from, the cache.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// The URL the response came from, after redirects.
+    pub final_url: Url,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+    /// The `Retry-After` delay of a fresh 429 or 503 response.
+    pub retry_after: Option<Duration>,
+}
+
+/// Caching metadata for a stored response, kept next to its body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub final_url: String,
+    pub status: u16,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// `Cache-Control: max-age`, in seconds.
+    pub max_age: Option<u64>,
+    /// `Cache-Control: no-cache`: always revalidate before use.
+    pub no_cache: bool,
+    /// When the response was stored or last revalidated (Unix seconds).
+    pub stored_at: u64,
+}
+
+impl CacheEntry {
+    /// Build an entry from response headers, or `None` if the response
+    /// must not be stored (`Cache-Control: no-store`, throttling or a
+    /// server error).
+    pub fn from_response(url: &Url, final_url: &Url, status: u16, headers: &HeaderMap) -> Option<Self> {
+        let directives = cache_directives(headers);
+        if status == 429 || status >= 500 || directives.iter().any(|d| d == "no-store") {
+            return None;
+        }
+
+        Some(Self {
+            url: url.to_string(),
+            final_url: final_url.to_string(),
+            status,
+            content_type: header_str(headers, CONTENT_TYPE),
+            etag: header_str(headers, ETAG),
+            last_modified: header_str(headers, LAST_MODIFIED),
+            max_age: directives
+                .iter()
+                .find_map(|d| d.strip_prefix("max-age=")?.trim_matches('"').parse().ok()),
+            no_cache: directives.iter().any(|d| d == "no-cache"),
+            stored_at: now(),
+        })
+    }
+
+    /// Whether the entry may be served without revalidation.
+    pub fn is_fresh(&self, at: u64) -> bool {
+        !self.no_cache
+            && self
+                .max_age
+                .is_some_and(|max_age| at.saturating_sub(self.stored_at) < max_age)
+    }
+
+    /// Whether the origin can answer a conditional request for the entry.
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+
+    /// Update freshness information after a `304 Not Modified`.
+    pub fn revalidated(&mut self, headers: &HeaderMap) {
+        let directives = cache_directives(headers);
+        if let Some(etag) = header_str(headers, ETAG) {
+            self.etag = Some(etag);
+        }
+        if let Some(max_age) = directives
+            .iter()
+            .find_map(|d| d.strip_prefix("max-age=")?.parse().ok())
+        {
+            self.max_age = Some(max_age);
+        }
+        self.stored_at = now();
+    }
+}
+
+/// An on-disk HTTP cache keyed by request URL.
+///
+/// Each response is stored as `<sha256(url)>.json` (metadata) and
+/// `<sha256(url)>.body` (raw body bytes) in the cache directory.
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .map_err(|e| AgentError::Cache(format!("cannot create {}: {}", dir.display(), e)))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// The stored entry and response for `url`, if any. Unreadable or
+    /// mismatched entries are treated as misses.
+    pub fn lookup(&self, url: &Url) -> Option<(CacheEntry, CachedResponse)> {
+        let (meta_path, body_path) = self.paths(url);
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(meta_path).ok()?).ok()?;
+        if entry.url != url.as_str() {
+            return None;
+        }
+        let body = fs::read(body_path).ok()?;
+        let response = CachedResponse {
+            final_url: Url::parse(&entry.final_url).ok()?,
+            status: entry.status,
+            content_type: entry.content_type.clone(),
+            body,
+            retry_after: None,
+        };
+        Some((entry, response))
+    }
+
+    /// Store a response. The body is written before the metadata, so a
+    /// crash never leaves metadata pointing at a missing or partial body.
+    pub fn store(&self, entry: &CacheEntry, body: &[u8]) -> Result<()> {
+        let url = Url::parse(&entry.url)?;
+        let (meta_path, body_path) = self.paths(&url);
+        let write = || -> std::io::Result<()> {
+            let tmp = body_path.with_extension("body.tmp");
+            fs::write(&tmp, body)?;
+            fs::rename(&tmp, &body_path)?;
+            self.write_meta(&meta_path, entry)
+        };
+        write().map_err(|e| AgentError::Cache(format!("cannot store {}: {}", entry.url, e)))
+    }
+
+    /// Replace the metadata of a stored response, e.g. after revalidation.
+    pub fn update(&self, entry: &CacheEntry) -> Result<()> {
+        let url = Url::parse(&entry.url)?;
+        let (meta_path, _) = self.paths(&url);
+        self.write_meta(&meta_path, entry)
+            .map_err(|e| AgentError::Cache(format!("cannot update {}: {}", entry.url, e)))
+    }
+
+    fn write_meta(&self, path: &Path, entry: &CacheEntry) -> std::io::Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        fs::rename(&tmp, path)
+    }
+
+    fn paths(&self, url: &Url) -> (PathBuf, PathBuf) {
+        let hash = format!("{:x}", Sha256::digest(url.as_str().as_bytes()));
+        (
+            self.dir.join(format!("{}.json", hash)),
+            self.dir.join(format!("{}.body", hash)),
+        )
+    }
+}
+
+fn cache_directives(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|d| d.trim().to_ascii_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect()
+}
+
+fn header_str(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let entry = CacheEntry::from_response(
+            &url,
+            &url,
+            200,
+            &headers(&[("cache-control", "public, max-age=60"), ("etag", "\"v1\"")]),
+        )
+        .unwrap();
+        assert_eq!(entry.max_age, Some(60));
+        assert!(entry.is_fresh(entry.stored_at + 59));
+        assert!(!entry.is_fresh(entry.stored_at + 60));
+        assert!(entry.has_validators());
+
+        let no_cache =
+            CacheEntry::from_response(&url, &url, 200, &headers(&[("cache-control", "no-cache, max-age=60")]))
+                .unwrap();
+        assert!(!no_cache.is_fresh(no_cache.stored_at));
+
+        assert!(CacheEntry::from_response(&url, &url, 200, &headers(&[("cache-control", "no-store")])).is_none());
+        assert!(CacheEntry::from_response(&url, &url, 503, &HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_store_and_lookup() {
+        let dir = std::env::temp_dir().join(format!("http-cache-test-{}", std::process::id()));
+        let cache = HttpCache::open(&dir).unwrap();
+        let url = Url::parse("https://example.com/a?b=1").unwrap();
+        let final_url = Url::parse("https://example.com/a/?b=1").unwrap();
+
+        assert!(cache.lookup(&url).is_none());
+        let entry = CacheEntry::from_response(&url, &final_url, 200, &HeaderMap::new()).unwrap();
+        cache.store(&entry, b"<h1>Hi</h1>").unwrap();
+
+        let (stored, response) = cache.lookup(&url).unwrap();
+        assert_eq!(stored.status, 200);
+        assert_eq!(response.final_url, final_url);
+        assert_eq!(response.body, b"<h1>Hi</h1>");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
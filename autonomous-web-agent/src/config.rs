@@ -1,12 +1,40 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct SafetyConfig {
     pub allowed_domains: Vec<String>,
     pub max_depth: usize,
     pub max_requests: usize,
+    /// Wall-clock limit for one run of the task, in seconds.
+    pub max_duration_secs: Option<u64>,
+    /// Limit on the total size of the response bodies received.
+    pub max_total_bytes: Option<u64>,
+    /// Requests allowed to each host. URLs on a host that has used up its
+    /// share are skipped; the crawl goes on elsewhere.
+    pub max_requests_per_domain: Option<usize>,
+    /// Pages fetched under a path prefix, e.g. `{"/blog/": 20}`. Further
+    /// URLs under a full prefix are skipped.
+    pub max_pages_per_path: HashMap<String, usize>,
+    /// Minimum delay between request starts to the same host.
     pub rate_limit_ms: u64,
+    /// Upper bound on a site's robots.txt `Crawl-delay`, so a huge value
+    /// cannot stall the crawl.
+    pub max_crawl_delay_ms: u64,
+    /// Maximum simultaneous connections to a single host.
+    pub max_connections_per_host: usize,
     pub blocked_content_patterns: Vec<String>,
+    /// Domains (and their subdomains) whose robots.txt is not fetched or
+    /// obeyed. Only list sites you own or have permission to crawl.
+    pub robots_exempt_domains: Vec<String>,
+    /// Regex patterns for form action URLs the agent may submit to. Forms
+    /// whose action matches none of them are never submitted.
+    pub allowed_form_actions: Vec<String>,
+    /// Allow/deny rules for the URLs the agent may request.
+    pub policy: PolicyConfig,
 }
 
 impl Default for SafetyConfig {
@@ -15,25 +43,441 @@ impl Default for SafetyConfig {
             allowed_domains: vec![],
             max_depth: 3,
             max_requests: 20,
+            max_duration_secs: None,
+            max_total_bytes: None,
+            max_requests_per_domain: None,
+            max_pages_per_path: HashMap::new(),
             rate_limit_ms: 1000,
+            max_crawl_delay_ms: 30_000,
+            max_connections_per_host: 2,
             blocked_content_patterns: vec![],
+            robots_exempt_domains: vec![],
+            allowed_form_actions: vec![],
+            policy: PolicyConfig::default(),
         }
     }
 }
 
+/// Whether a policy rule lets a URL through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Allow,
+    Deny,
+}
+
+impl fmt::Display for RuleAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleAction::Allow => write!(f, "allow"),
+            RuleAction::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// A URL policy rule. The rule matches a URL when every condition it sets
+/// matches; a condition matches when any of its values does. A rule with
+/// no conditions matches every URL.
 #[derive(Debug, Clone, Deserialize)]
+pub struct PolicyRule {
+    /// Name reported when the rule fires; defaults to `rules[<index>]`.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub action: RuleAction,
+    #[serde(default)]
+    pub schemes: Vec<String>,
+    /// Host names; each also matches its subdomains.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Path globs: `*` matches within a path segment, `**` across
+    /// segments and `?` a single character.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Query parameters, as `name` (present with any value) or
+    /// `name=value`.
+    #[serde(default)]
+    pub query_params: Vec<String>,
+    /// Ports, including the scheme's default port.
+    #[serde(default)]
+    pub ports: Vec<u16>,
+    /// CIDR ranges (or single addresses) matched against the host's IP
+    /// address, literal or resolved.
+    #[serde(default)]
+    pub ip_ranges: Vec<String>,
+}
+
+/// Which URLs the agent may request.
+///
+/// Hosts that are or resolve to non-public addresses are refused first,
+/// then `rules` are checked in order and the first match decides. URLs no
+/// rule matches must be in `SafetyConfig::allowed_domains`, if it is set,
+/// and otherwise get `default_action`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    pub rules: Vec<PolicyRule>,
+    pub default_action: RuleAction,
+    /// Refuse private, loopback, link-local and other non-public
+    /// addresses, including on redirects and at connection time, so the
+    /// agent cannot be pointed at internal services.
+    pub block_private_addresses: bool,
+    /// Non-public ranges that may be requested anyway, e.g. `127.0.0.1/32`
+    /// for a local test server.
+    pub allowed_private_ranges: Vec<String>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            rules: vec![],
+            default_action: RuleAction::Allow,
+            block_private_addresses: true,
+            allowed_private_ranges: vec![],
+        }
+    }
+}
+
+/// How URLs are normalized before deduplication, and how similar two pages'
+/// text must be to count as near-duplicates.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DedupConfig {
+    pub strip_fragment: bool,
+    pub strip_trailing_slash: bool,
+    pub sort_query: bool,
+    /// Query parameters to drop; a trailing `*` matches a prefix (`utm_*`).
+    pub strip_params: Vec<String>,
+    /// Pages whose text SimHash differs from an earlier page's by at most
+    /// this many bits are skipped; 3 catches mirrors and print views. Off
+    /// by default, since templated pages with little text of their own,
+    /// such as listings, can look alike.
+    pub near_duplicate_distance: Option<u32>,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            strip_fragment: true,
+            strip_trailing_slash: true,
+            sort_query: true,
+            strip_params: ["utm_*", "fbclid", "gclid", "mc_cid", "mc_eid"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            near_duplicate_distance: None,
+        }
+    }
+}
+
+/// The order in which the frontier hands out URLs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Breadth-first: URLs in discovery order.
+    Bfs,
+    /// Depth-first: the most recently discovered URL first.
+    Dfs,
+    /// Highest score first, as ranked by the frontier's scorer.
+    BestFirst,
+}
+
+/// Frontier ordering, and the weights used by the default link scorer.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FrontierConfig {
+    /// Defaults to breadth-first; the weights below only apply to
+    /// best-first.
+    pub strategy: Strategy,
+    /// Words that make a link more relevant when they appear in its anchor
+    /// text or URL.
+    pub keywords: Vec<String>,
+    /// Score lost per level of depth.
+    pub depth_weight: f64,
+    /// Score for a URL fully matched by a follow pattern, scaled by the
+    /// fraction of the URL the match covers.
+    pub pattern_weight: f64,
+    /// Score for a link matching every keyword, scaled by the fraction
+    /// matched.
+    pub keyword_weight: f64,
+    /// Score for a link on the same host as the page it was found on.
+    pub same_host_weight: f64,
+}
+
+impl Default for FrontierConfig {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::Bfs,
+            keywords: vec![],
+            depth_weight: 1.0,
+            pattern_weight: 1.0,
+            keyword_weight: 2.0,
+            same_host_weight: 0.5,
+        }
+    }
+}
+
+/// Retries of transient fetch failures (timeouts, connection errors,
+/// 429 and 5xx responses). Form POSTs are not idempotent and are never
+/// retried.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for every further retry.
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Randomize delays so concurrent retries do not arrive together.
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+            jitter: true,
+        }
+    }
+}
+
+/// The kinds of response body the agent can process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentKind {
+    /// `text/html` and `application/xhtml+xml`.
+    Html,
+    /// `text/plain`.
+    Text,
+    /// `application/json` and `+json` types.
+    Json,
+    /// `application/xml`, `text/xml` and `+xml` types such as RSS and Atom.
+    Xml,
+}
+
+impl fmt::Display for ContentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentKind::Html => write!(f, "html"),
+            ContentKind::Text => write!(f, "text"),
+            ContentKind::Json => write!(f, "json"),
+            ContentKind::Xml => write!(f, "xml"),
+        }
+    }
+}
+
+/// Which responses are downloaded and processed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ContentConfig {
+    /// Larger bodies are not downloaded; the page is skipped.
+    pub max_body_bytes: u64,
+    /// Non-HTML kinds to extract text from. HTML is always processed;
+    /// pages of any other type are skipped before their body is read.
+    pub extract: Vec<ContentKind>,
+}
+
+impl Default for ContentConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 10 * 1024 * 1024,
+            extract: vec![],
+        }
+    }
+}
+
+/// Kinds of sensitive data the redaction stage looks for in extracted
+/// values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveKind {
+    Email,
+    /// Phone numbers in international (`+44 20 7946 0958`) or North
+    /// American (`(555) 123-4567`, `555-123-4567`) notation.
+    Phone,
+    /// Card numbers of 13 to 19 digits that pass the Luhn check.
+    CreditCard,
+    /// Well-known API key and token formats (AWS, GitHub, Slack, Stripe,
+    /// Google), JWTs and bearer tokens.
+    ApiToken,
+}
+
+impl fmt::Display for SensitiveKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensitiveKind::Email => write!(f, "email"),
+            SensitiveKind::Phone => write!(f, "phone"),
+            SensitiveKind::CreditCard => write!(f, "credit_card"),
+            SensitiveKind::ApiToken => write!(f, "api_token"),
+        }
+    }
+}
+
+/// What to do with sensitive data found in an extracted value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionAction {
+    /// Leave it in place.
+    Keep,
+    /// Replace it with `[REDACTED:<kind>]`.
+    Mask,
+    /// Drop the whole extracted item.
+    Drop,
+}
+
+/// How each kind of sensitive data is redacted from extracted items.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub email: RedactionAction,
+    pub phone: RedactionAction,
+    pub credit_card: RedactionAction,
+    pub api_token: RedactionAction,
+}
+
+impl RedactionConfig {
+    /// The same action for every kind.
+    pub fn all(action: RedactionAction) -> Self {
+        Self {
+            email: action,
+            phone: action,
+            credit_card: action,
+            api_token: action,
+        }
+    }
+
+    pub fn action(&self, kind: SensitiveKind) -> RedactionAction {
+        match kind {
+            SensitiveKind::Email => self.email,
+            SensitiveKind::Phone => self.phone,
+            SensitiveKind::CreditCard => self.credit_card,
+            SensitiveKind::ApiToken => self.api_token,
+        }
+    }
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self::all(RedactionAction::Mask)
+    }
+}
+
+/// On-disk HTTP response caching.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Cache directory. Caching is disabled when unset.
+    pub dir: Option<PathBuf>,
+    /// Serve every request from the cache without touching the network,
+    /// failing requests that were never recorded.
+    pub offline: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct AgentConfig {
     pub safety: SafetyConfig,
+    pub dedup: DedupConfig,
+    pub frontier: FrontierConfig,
+    pub cache: CacheConfig,
+    pub retry: RetryConfig,
+    pub content: ContentConfig,
+    pub redaction: RedactionConfig,
     pub user_agent: String,
     pub request_timeout_secs: u64,
+    /// Maximum number of fetches in flight across all hosts.
+    pub max_concurrent_requests: usize,
 }
 
 impl Default for AgentConfig {
     fn default() -> Self {
         Self {
             safety: SafetyConfig::default(),
+            dedup: DedupConfig::default(),
+            frontier: FrontierConfig::default(),
+            cache: CacheConfig::default(),
+            retry: RetryConfig::default(),
+            content: ContentConfig::default(),
+            redaction: RedactionConfig::default(),
             user_agent: "AutonomousWebAgent-POC/0.1".to_string(),
             request_timeout_secs: 30,
+            max_concurrent_requests: 4,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_are_conservative() {
+        let config = AgentConfig::default();
+        assert!(config.safety.policy.block_private_addresses);
+        assert_eq!(config.safety.policy.default_action, RuleAction::Allow);
+        assert!(config.safety.allowed_form_actions.is_empty());
+        assert_eq!(config.safety.max_crawl_delay_ms, 30_000);
+        assert_eq!(config.dedup.near_duplicate_distance, None);
+        assert_eq!(config.frontier.strategy, Strategy::Bfs);
+        assert!(config.content.extract.is_empty());
+        assert!(!config.cache.offline);
+        assert_eq!(
+            config.redaction.action(SensitiveKind::ApiToken),
+            RedactionAction::Mask
+        );
+    }
+
+    #[test]
+    fn test_partial_config_keeps_defaults() {
+        let config: AgentConfig = serde_json::from_str(
+            r#"{
+                "safety": {"max_requests": 5, "policy": {"allowed_private_ranges": ["127.0.0.1/32"]}},
+                "frontier": {"strategy": "best_first", "keywords": ["rust"]},
+                "content": {"extract": ["json", "xml"]},
+                "redaction": {"email": "keep", "credit_card": "drop"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.safety.max_requests, 5);
+        assert_eq!(config.safety.max_depth, SafetyConfig::default().max_depth);
+        assert!(config.safety.policy.block_private_addresses);
+        assert_eq!(config.frontier.strategy, Strategy::BestFirst);
+        assert_eq!(
+            config.frontier.keyword_weight,
+            FrontierConfig::default().keyword_weight
+        );
+        assert_eq!(
+            config.content.extract,
+            [ContentKind::Json, ContentKind::Xml]
+        );
+        assert_eq!(config.retry.max_retries, RetryConfig::default().max_retries);
+
+        let redaction = &config.redaction;
+        assert_eq!(
+            redaction.action(SensitiveKind::Email),
+            RedactionAction::Keep
+        );
+        assert_eq!(
+            redaction.action(SensitiveKind::CreditCard),
+            RedactionAction::Drop
+        );
+        assert_eq!(
+            redaction.action(SensitiveKind::Phone),
+            RedactionAction::Mask
+        );
+    }
+
+    #[test]
+    fn test_policy_rule_fields_default_to_empty() {
+        let rule: PolicyRule =
+            serde_json::from_str(r#"{"action": "deny", "ports": [8080]}"#).unwrap();
+        assert_eq!(rule.action, RuleAction::Deny);
+        assert_eq!(rule.ports, [8080]);
+        assert!(rule.name.is_none() && rule.hosts.is_empty() && rule.paths.is_empty());
+        assert!(serde_json::from_str::<PolicyRule>(r#"{"action": "block"}"#).is_err());
+        assert_eq!(RuleAction::Deny.to_string(), "deny");
+        assert_eq!(SensitiveKind::CreditCard.to_string(), "credit_card");
+    }
+}
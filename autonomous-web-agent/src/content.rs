@@ -0,0 +1,288 @@
+use crate::config::ContentKind;
+use encoding_rs::{Encoding, UTF_8};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_json::Value;
+use url::Url;
+
+/// How far into a document to look for an in-document charset declaration
+/// (the HTML prescan limit).
+const CHARSET_PRESCAN_BYTES: usize = 1024;
+
+/// A parsed `Content-Type` header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaType {
+    /// The lowercased `type/subtype`, without parameters.
+    pub essence: String,
+    pub charset: Option<String>,
+}
+
+impl MediaType {
+    pub fn parse(header: &str) -> Self {
+        let mut parts = header.split(';');
+        let essence = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let charset = parts.find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches('"').to_string())
+        });
+        Self { essence, charset }
+    }
+
+    /// The kind of content, or `None` for types the agent cannot process
+    /// (images, PDFs, archives, ...).
+    pub fn kind(&self) -> Option<ContentKind> {
+        let essence = self.essence.as_str();
+        match essence {
+            "text/html" | "application/xhtml+xml" => Some(ContentKind::Html),
+            "text/plain" => Some(ContentKind::Text),
+            "application/json" | "text/json" => Some(ContentKind::Json),
+            "application/xml" | "text/xml" => Some(ContentKind::Xml),
+            _ if essence.ends_with("+json") => Some(ContentKind::Json),
+            _ if essence.ends_with("+xml") => Some(ContentKind::Xml),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of a response from its `Content-Type` header, if any. A
+/// response without one is treated as HTML.
+pub fn kind_of(content_type: Option<&str>) -> Option<ContentKind> {
+    match content_type {
+        Some(header) => MediaType::parse(header).kind(),
+        None => Some(ContentKind::Html),
+    }
+}
+
+/// Decode a response body to text.
+///
+/// A byte order mark wins, then the `Content-Type` charset, then a charset
+/// declared in the document itself (`<meta charset>` for HTML, the XML
+/// declaration for XML); anything else is read as UTF-8. JSON is always
+/// UTF-8. Invalid sequences become U+FFFD.
+pub fn decode(body: &[u8], content_type: Option<&str>, kind: ContentKind) -> String {
+    let declared = match kind {
+        ContentKind::Json => None,
+        _ => content_type
+            .and_then(|header| MediaType::parse(header).charset)
+            .or_else(|| sniff_charset(body, kind)),
+    };
+    let encoding = declared
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        // A document cannot declare itself UTF-16 in an ASCII-compatible
+        // header; that label is only honored through a BOM.
+        .filter(|encoding| encoding.is_ascii_compatible())
+        .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+fn sniff_charset(body: &[u8], kind: ContentKind) -> Option<String> {
+    let head = String::from_utf8_lossy(&body[..body.len().min(CHARSET_PRESCAN_BYTES)])
+        .to_ascii_lowercase();
+    let rest = match kind {
+        ContentKind::Html => {
+            let meta = head.find("<meta")?;
+            let at = head[meta..].find("charset=")? + meta;
+            &head[at + "charset=".len()..]
+        }
+        ContentKind::Xml => {
+            let decl = head.strip_prefix("<?xml")?;
+            let decl = &decl[..decl.find("?>")?];
+            let at = decl.find("encoding=")?;
+            &decl[at + "encoding=".len()..]
+        }
+        ContentKind::Text | ContentKind::Json => return None,
+    };
+    let label: String = rest
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+        .collect();
+    (!label.is_empty()).then_some(label)
+}
+
+/// Text, title and links of a non-HTML document.
+#[derive(Debug, Default, PartialEq)]
+pub struct Document {
+    pub title: Option<String>,
+    pub text: String,
+    pub links: Vec<Url>,
+}
+
+/// Read a plain-text, JSON or XML document. For JSON the text is the
+/// document itself; for XML it is the document's text nodes, and feed
+/// (`<link>`) URLs are returned as links.
+pub fn read_document(kind: ContentKind, body: &str, base: &Url) -> Document {
+    match kind {
+        ContentKind::Xml => read_xml(body, base),
+        ContentKind::Html | ContentKind::Text | ContentKind::Json => Document {
+            text: body.to_string(),
+            ..Default::default()
+        },
+    }
+}
+
+/// The value extracted from a non-HTML document: parsed JSON, or its text.
+pub fn document_value(kind: ContentKind, text: &str) -> Value {
+    match kind {
+        ContentKind::Json => {
+            serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.trim().to_string()))
+        }
+        _ => Value::String(text.trim().to_string()),
+    }
+}
+
+fn read_xml(body: &str, base: &Url) -> Document {
+    let mut reader = Reader::from_str(body);
+    reader.trim_text(true);
+
+    let mut document = Document::default();
+    let mut texts: Vec<String> = Vec::new();
+    let mut element: Vec<Vec<u8>> = Vec::new();
+
+    let add_link = |document: &mut Document, href: &str| {
+        if let Ok(url) = base.join(href.trim()) {
+            if matches!(url.scheme(), "http" | "https") && !document.links.contains(&url) {
+                document.links.push(url);
+            }
+        }
+    };
+
+    loop {
+        let text = match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                if let Some(href) = link_href(&e) {
+                    add_link(&mut document, &href);
+                }
+                element.push(e.local_name().as_ref().to_vec());
+                continue;
+            }
+            Ok(Event::Empty(e)) => {
+                if let Some(href) = link_href(&e) {
+                    add_link(&mut document, &href);
+                }
+                continue;
+            }
+            Ok(Event::End(_)) => {
+                element.pop();
+                continue;
+            }
+            Ok(Event::Text(t)) => match t.unescape() {
+                Ok(text) => text.into_owned(),
+                Err(_) => continue,
+            },
+            Ok(Event::CData(t)) => String::from_utf8_lossy(&t.into_inner()).into_owned(),
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => continue,
+        };
+
+        match element.last().map(Vec::as_slice) {
+            // RSS: <link>https://...</link>
+            Some(b"link") => add_link(&mut document, &text),
+            Some(b"title") if document.title.is_none() => document.title = Some(text.clone()),
+            _ => {}
+        }
+        texts.push(text);
+    }
+
+    document.text = texts.join(" ");
+    document
+}
+
+/// The `href` of an Atom `<link>` element.
+fn link_href(e: &BytesStart) -> Option<String> {
+    if e.local_name().as_ref() != b"link" {
+        return None;
+    }
+    let rel = e.try_get_attribute("rel").ok().flatten();
+    if rel.is_some_and(|rel| !matches!(rel.value.as_ref(), b"alternate")) {
+        return None;
+    }
+    let href = e.try_get_attribute("href").ok().flatten()?;
+    Some(href.unescape_value().ok()?.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_type() {
+        let media_type = MediaType::parse("Text/HTML; Charset=\"ISO-8859-1\"");
+        assert_eq!(media_type.essence, "text/html");
+        assert_eq!(media_type.charset.as_deref(), Some("ISO-8859-1"));
+
+        assert_eq!(kind_of(Some("application/rss+xml")), Some(ContentKind::Xml));
+        assert_eq!(
+            kind_of(Some("application/ld+json")),
+            Some(ContentKind::Json)
+        );
+        assert_eq!(kind_of(Some("application/pdf")), None);
+        assert_eq!(kind_of(None), Some(ContentKind::Html));
+    }
+
+    #[test]
+    fn test_decode_charsets() {
+        // "café" in Latin-1.
+        let latin1 = b"<html><head><meta charset=\"iso-8859-1\"></head><p>caf\xe9</p>";
+        assert!(decode(latin1, Some("text/html"), ContentKind::Html).contains("café"));
+        // The header overrides the document.
+        let header = decode(
+            b"caf\xe9",
+            Some("text/plain; charset=windows-1252"),
+            ContentKind::Text,
+        );
+        assert_eq!(header, "café");
+        // A BOM overrides both.
+        let bom = decode(
+            b"\xef\xbb\xbfcaf\xc3\xa9",
+            Some("text/plain; charset=latin1"),
+            ContentKind::Text,
+        );
+        assert_eq!(bom, "café");
+
+        let xml = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>caf\xe9</a>";
+        assert!(decode(xml, Some("application/xml"), ContentKind::Xml).contains("café"));
+        assert_eq!(decode(b"caf\xc3\xa9", None, ContentKind::Html), "café");
+    }
+
+    #[test]
+    fn test_read_feeds() {
+        let base = Url::parse("https://example.com/feed").unwrap();
+        let rss = r#"<?xml version="1.0"?><rss><channel><title>News</title>
+            <link>https://example.com/</link>
+            <item><title>First &amp; best</title><link>/posts/1</link></item>
+            <item><title><![CDATA[Second]]></title><link>https://example.com/posts/2</link></item>
+            </channel></rss>"#;
+        let document = read_document(ContentKind::Xml, rss, &base);
+        assert_eq!(document.title.as_deref(), Some("News"));
+        assert_eq!(document.links.len(), 3);
+        assert_eq!(document.links[1].as_str(), "https://example.com/posts/1");
+        assert!(document.text.contains("First & best /posts/1"));
+        assert!(document.text.contains("Second"));
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Blog</title>
+            <link rel="self" href="/feed"/><entry><link href="/posts/3"/></entry></feed>"#;
+        let document = read_document(ContentKind::Xml, atom, &base);
+        assert_eq!(
+            document.links,
+            vec![Url::parse("https://example.com/posts/3").unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_document_value() {
+        let json = document_value(ContentKind::Json, r#"{"a": [1, 2]}"#);
+        assert_eq!(json["a"][1], 2);
+        assert_eq!(
+            document_value(ContentKind::Json, "not json"),
+            Value::String("not json".to_string())
+        );
+        assert_eq!(
+            document_value(ContentKind::Text, " hi\n"),
+            Value::String("hi".to_string())
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::config::DedupConfig;
+use url::Url;
+
+/// The key under which a URL is deduplicated: the URL with fragments,
+/// tracking parameters, parameter order and trailing slashes normalized
+/// away as configured. Scheme and host case and default ports are already
+/// normalized by URL parsing.
+pub fn canonical_key(url: &Url, config: &DedupConfig) -> String {
+    let mut url = url.clone();
+
+    if config.strip_fragment {
+        url.set_fragment(None);
+    }
+
+    if url.query().is_some() {
+        let mut params: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(name, _)| !config.strip_params.iter().any(|p| param_matches(p, name)))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        if config.sort_query {
+            params.sort();
+        }
+
+        if params.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(&params);
+        }
+    }
+
+    if config.strip_trailing_slash && url.path().len() > 1 && url.path().ends_with('/') {
+        let path = url.path().trim_end_matches('/').to_string();
+        url.set_path(&path);
+    }
+
+    url.to_string()
+}
+
+/// Match a query parameter name against a pattern; a trailing `*` matches
+/// any suffix (`utm_*`).
+fn param_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// A 64-bit SimHash of the words in `text`. Texts that share most of their
+/// words have fingerprints a small Hamming distance apart.
+pub fn simhash(text: &str) -> u64 {
+    let mut weights = [0i64; 64];
+    let words = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty());
+
+    for word in words {
+        let hash = fnv1a(&word.to_lowercase());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0)
+        .fold(0, |acc, (bit, _)| acc | (1 << bit))
+}
+
+/// FNV-1a, used instead of the std hasher so fingerprints are stable
+/// across builds and can be persisted.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Content fingerprints of the pages processed so far.
+#[derive(Debug, Default)]
+pub struct ContentIndex {
+    fingerprints: Vec<(String, u64)>,
+}
+
+impl ContentIndex {
+    pub fn from_fingerprints(fingerprints: Vec<(String, u64)>) -> Self {
+        Self { fingerprints }
+    }
+
+    pub fn fingerprints(&self) -> &[(String, u64)] {
+        &self.fingerprints
+    }
+
+    /// The URL of an indexed page within `max_distance` bits of `fingerprint`.
+    pub fn find_near(&self, fingerprint: u64, max_distance: u32) -> Option<&str> {
+        self.fingerprints
+            .iter()
+            .find(|(_, fp)| (fp ^ fingerprint).count_ones() <= max_distance)
+            .map(|(url, _)| url.as_str())
+    }
+
+    pub fn insert(&mut self, url: String, fingerprint: u64) {
+        self.fingerprints.push((url, fingerprint));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(url: &str) -> String {
+        canonical_key(&Url::parse(url).unwrap(), &DedupConfig::default())
+    }
+
+    #[test]
+    fn test_canonical_key() {
+        assert_eq!(key("https://Example.com:443/docs/#intro"), "https://example.com/docs");
+        assert_eq!(
+            key("https://example.com/p?b=2&utm_source=x&a=1&fbclid=y"),
+            "https://example.com/p?a=1&b=2"
+        );
+        assert_eq!(key("https://example.com/?utm_medium=mail"), "https://example.com/");
+    }
+
+    #[test]
+    fn test_canonical_key_respects_config() {
+        let config = DedupConfig {
+            strip_fragment: false,
+            sort_query: false,
+            strip_trailing_slash: false,
+            strip_params: vec![],
+            ..Default::default()
+        };
+        let url = Url::parse("https://example.com/a/?z=1&a=2#top").unwrap();
+        assert_eq!(canonical_key(&url, &config), url.to_string());
+    }
+
+    #[test]
+    fn test_simhash_near_duplicates() {
+        let article = "The quick brown fox jumps over the lazy dog while the farmer watches \
+                       from the porch and the sun sets slowly behind the distant hills";
+        let with_footer = format!("{} Copyright 2024", article);
+        let other = "Rust is a systems programming language focused on safety speed and \
+                     concurrency without a garbage collector";
+
+        let mut index = ContentIndex::default();
+        index.insert("https://example.com/a".to_string(), simhash(article));
+
+        assert_eq!(
+            index.find_near(simhash(&with_footer), 3),
+            Some("https://example.com/a")
+        );
+        assert_eq!(index.find_near(simhash(other), 3), None);
+    }
+}
@@ -16,6 +16,27 @@ pub enum AgentError {
 
     #[error("Extraction error: {0}")]
     Extraction(String),
+
+    #[error("Sitemap error: {0}")]
+    Sitemap(String),
+
+    #[error("Task file error: {0}")]
+    TaskFile(String),
+
+    #[error("Crawl state error: {0}")]
+    State(String),
+
+    #[error("Cache error: {0}")]
+    Cache(String),
+
+    #[error("Content skipped: {0}")]
+    Content(String),
+
+    #[error("Output error: {0}")]
+    Output(String),
+
+    #[error("Invalid policy: {0}")]
+    Policy(String),
 }
 
 pub type Result<T> = std::result::Result<T, AgentError>;
@@ -1,31 +1,94 @@
+use crate::article;
 use crate::browser::ParsedPage;
-use crate::task::ExtractionGoal;
-use scraper::Selector;
-use serde::Serialize;
+use crate::config::ContentKind;
+use crate::content;
+use crate::metadata;
+use crate::task::{ExtractionGoal, FieldSpec};
+use crate::transform;
+use scraper::{ElementRef, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractedItem {
     pub source_url: String,
     pub field: String,
-    pub value: String,
+    /// A string for text and link goals; a JSON object for records.
+    pub value: Value,
+}
+
+impl ExtractedItem {
+    /// The value as display text: strings as-is, anything else as JSON.
+    pub fn value_text(&self) -> String {
+        match &self.value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
 }
 
 pub fn extract(page: &ParsedPage, goal: &ExtractionGoal) -> Vec<ExtractedItem> {
+    // Selectors do not apply to plain-text, JSON or XML documents; their
+    // content is extracted whole, under the kind's name.
+    if page.kind != ContentKind::Html && !matches!(goal, ExtractionGoal::CollectLinks) {
+        return extract_document(page);
+    }
+
     match goal {
         ExtractionGoal::CollectLinks => extract_links(page),
         ExtractionGoal::ExtractText { selectors } => extract_text(page, selectors),
         ExtractionGoal::ExtractStructured { fields } => extract_structured(page, fields),
+        ExtractionGoal::ExtractRecords {
+            name,
+            container,
+            fields,
+        } => extract_records(page, name, container, fields),
+        ExtractionGoal::ExtractArticle => extract_article(page),
+        ExtractionGoal::ExtractMetadata { types } => extract_metadata(page, types),
     }
 }
 
+fn extract_document(page: &ParsedPage) -> Vec<ExtractedItem> {
+    if page.text_content.trim().is_empty() {
+        return vec![];
+    }
+    vec![ExtractedItem {
+        source_url: page.url.to_string(),
+        field: page.kind.to_string(),
+        value: content::document_value(page.kind, &page.text_content),
+    }]
+}
+
 fn extract_links(page: &ParsedPage) -> Vec<ExtractedItem> {
     page.links
         .iter()
         .map(|link| ExtractedItem {
             source_url: page.url.to_string(),
             field: "link".to_string(),
-            value: link.to_string(),
+            value: Value::String(link.to_string()),
+        })
+        .collect()
+}
+
+fn extract_article(page: &ParsedPage) -> Vec<ExtractedItem> {
+    article::extract(&page.document)
+        .map(|article| ExtractedItem {
+            source_url: page.url.to_string(),
+            field: "article".to_string(),
+            value: serde_json::to_value(article).unwrap_or(Value::Null),
+        })
+        .into_iter()
+        .collect()
+}
+
+fn extract_metadata(page: &ParsedPage, types: &[String]) -> Vec<ExtractedItem> {
+    metadata::extract(&page.document)
+        .into_iter()
+        .filter(|m| m.matches_types(types))
+        .map(|m| ExtractedItem {
+            source_url: page.url.to_string(),
+            field: m.source.to_string(),
+            value: m.value,
         })
         .collect()
 }
@@ -45,7 +108,7 @@ fn extract_text(page: &ParsedPage, selectors: &[String]) -> Vec<ExtractedItem> {
                 items.push(ExtractedItem {
                     source_url: page.url.to_string(),
                     field: selector_str.clone(),
-                    value: text,
+                    value: Value::String(text),
                 });
             }
         }
@@ -72,7 +135,7 @@ fn extract_structured(
                 items.push(ExtractedItem {
                     source_url: page.url.to_string(),
                     field: field_name.clone(),
-                    value: text,
+                    value: Value::String(text),
                 });
             }
         }
@@ -80,3 +143,188 @@ fn extract_structured(
 
     items
 }
+
+fn extract_records(
+    page: &ParsedPage,
+    name: &str,
+    container: &str,
+    fields: &[FieldSpec],
+) -> Vec<ExtractedItem> {
+    let container_selector = match Selector::parse(container) {
+        Ok(s) => s,
+        Err(_) => return vec![],
+    };
+
+    let targets: Vec<FieldTarget> = fields
+        .iter()
+        .map(|f| match &f.selector {
+            Some(sel) => Selector::parse(sel)
+                .map(FieldTarget::Descendant)
+                .unwrap_or(FieldTarget::Invalid),
+            None => FieldTarget::Container,
+        })
+        .collect();
+
+    page.document
+        .select(&container_selector)
+        .filter_map(|element| {
+            let mut record = Map::new();
+            for (field, target) in fields.iter().zip(&targets) {
+                let value = field_value(element, target, field)
+                    .and_then(|raw| transform::apply_all(&field.transforms, raw, &page.url));
+                match value {
+                    Some(v) => {
+                        record.insert(field.name.clone(), v);
+                    }
+                    None if field.required => return None,
+                    None => {
+                        record.insert(field.name.clone(), Value::Null);
+                    }
+                }
+            }
+            Some(ExtractedItem {
+                source_url: page.url.to_string(),
+                field: name.to_string(),
+                value: Value::Object(record),
+            })
+        })
+        .collect()
+}
+
+/// Where a record field is read from, relative to its container.
+enum FieldTarget {
+    Container,
+    Descendant(Selector),
+    /// The selector failed to parse. It never matches, so a required field
+    /// with a bad selector drops every record rather than silently passing.
+    Invalid,
+}
+
+/// Evaluate a field against a record container: the first matching
+/// descendant (or the container itself), as an attribute or text.
+fn field_value(container: ElementRef, target: &FieldTarget, field: &FieldSpec) -> Option<String> {
+    let element = match target {
+        FieldTarget::Container => container,
+        FieldTarget::Descendant(sel) => container.select(sel).next()?,
+        FieldTarget::Invalid => return None,
+    };
+
+    let value = match &field.attribute {
+        Some(attr) => element.value().attr(attr)?.trim().to_string(),
+        None => element.text().collect::<Vec<_>>().join(" ").trim().to_string(),
+    };
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::{Browser, PageContent};
+    use url::Url;
+
+    fn page(html: &str) -> ParsedPage {
+        Browser::parse(&PageContent {
+            url: Url::parse("https://shop.example/list").unwrap(),
+            status: 200,
+            kind: ContentKind::Html,
+            body: html.to_string(),
+            retry_after: None,
+        })
+    }
+
+    const PRODUCTS: &str = r#"
+        <div class="product"><h2>Kettle</h2><span class="price">$25</span>
+            <a href="/p/kettle">more</a></div>
+        <div class="product"><h2>Toaster</h2>
+            <a href="/p/toaster">more</a></div>
+        <div class="product"><span class="price">$5</span></div>
+    "#;
+
+    fn fields() -> Vec<FieldSpec> {
+        vec![
+            "name=h2!".parse().unwrap(),
+            "price=.price".parse().unwrap(),
+            "link=a@href".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_records_keep_fields_together() {
+        let items = extract_records(&page(PRODUCTS), "product", ".product", &fields());
+
+        // The third product has no name, which is required.
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].field, "product");
+        assert_eq!(
+            items[0].value,
+            serde_json::json!({"name": "Kettle", "price": "$25", "link": "/p/kettle"})
+        );
+        assert_eq!(
+            items[1].value,
+            serde_json::json!({"name": "Toaster", "price": null, "link": "/p/toaster"})
+        );
+    }
+
+    #[test]
+    fn test_record_transforms_produce_typed_values() {
+        let fields: Vec<FieldSpec> = vec![
+            "name=h2!".parse().unwrap(),
+            "price=.price!|currency".parse().unwrap(),
+            "link=a@href|url".parse().unwrap(),
+        ];
+        let items = extract_records(&page(PRODUCTS), "product", ".product", &fields);
+
+        // Toaster has no price and the last product no name.
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].value,
+            serde_json::json!({
+                "name": "Kettle",
+                "price": {"amount": 25, "currency": "USD"},
+                "link": "https://shop.example/p/kettle",
+            })
+        );
+    }
+
+    #[test]
+    fn test_field_spec_shorthand() {
+        let spec: FieldSpec = "image=img.hero@src!".parse().unwrap();
+        assert_eq!(spec.name, "image");
+        assert_eq!(spec.selector.as_deref(), Some("img.hero"));
+        assert_eq!(spec.attribute.as_deref(), Some("src"));
+        assert!(spec.required);
+        assert!(spec.transforms.is_empty());
+
+        let typed: FieldSpec = "when=time@datetime|date|ws".parse().unwrap();
+        assert_eq!(typed.transforms.len(), 2);
+        assert!("x=.y|nope".parse::<FieldSpec>().is_err());
+
+        let own: FieldSpec = "id=@data-id".parse().unwrap();
+        assert_eq!(own.selector, None);
+        assert!("no-equals".parse::<FieldSpec>().is_err());
+    }
+
+    #[test]
+    fn test_non_html_documents_are_extracted_whole() {
+        let feed = Browser::parse(&PageContent {
+            url: Url::parse("https://shop.example/api/items").unwrap(),
+            status: 200,
+            kind: ContentKind::Json,
+            body: r#"{"items": [{"name": "Kettle"}]}"#.to_string(),
+            retry_after: None,
+        });
+        let goal = ExtractionGoal::ExtractText {
+            selectors: vec!["h1".to_string()],
+        };
+
+        let items = extract(&feed, &goal);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].field, "json");
+        assert_eq!(items[0].value["items"][0]["name"], "Kettle");
+    }
+}
@@ -0,0 +1,246 @@
+use crate::browser::ParsedPage;
+use scraper::{ElementRef, Selector};
+use std::collections::HashMap;
+use std::fmt;
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormMethod {
+    Get,
+    Post,
+}
+
+impl fmt::Display for FormMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormMethod::Get => write!(f, "GET"),
+            FormMethod::Post => write!(f, "POST"),
+        }
+    }
+}
+
+/// An HTML form found on a page.
+#[derive(Debug, Clone)]
+pub struct Form {
+    /// The absolute URL the form submits to.
+    pub action: Url,
+    pub method: FormMethod,
+    /// Successful controls with their default values, in document order:
+    /// text-like inputs, hidden inputs, checked checkboxes and radios,
+    /// selected options and textareas. Buttons are not included.
+    pub fields: Vec<(String, String)>,
+}
+
+/// A filled-in form, ready to be sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormRequest {
+    pub method: FormMethod,
+    /// For GET forms, the action URL with the fields as its query.
+    pub url: Url,
+    /// Form-encoded fields for POST forms; empty for GET.
+    pub body: Vec<(String, String)>,
+}
+
+impl FormRequest {
+    /// Identifies the submission for deduplication.
+    pub fn key(&self) -> String {
+        let body: Vec<String> = self.body.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        format!("{} {} {}", self.method, self.url, body.join("&"))
+    }
+}
+
+impl Form {
+    /// Fill the form with `values`, overriding field defaults. Values for
+    /// names the form does not declare are appended.
+    pub fn fill(&self, values: &HashMap<String, String>) -> FormRequest {
+        let mut fields: Vec<(String, String)> = self
+            .fields
+            .iter()
+            .map(|(name, default)| {
+                let value = values.get(name).unwrap_or(default);
+                (name.clone(), value.clone())
+            })
+            .collect();
+
+        let mut extra: Vec<(&String, &String)> = values
+            .iter()
+            .filter(|(name, _)| !self.fields.iter().any(|(n, _)| n == *name))
+            .collect();
+        extra.sort();
+        fields.extend(extra.into_iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        match self.method {
+            FormMethod::Get => {
+                let mut url = self.action.clone();
+                url.set_fragment(None);
+                url.query_pairs_mut().clear().extend_pairs(&fields);
+                FormRequest {
+                    method: FormMethod::Get,
+                    url,
+                    body: vec![],
+                }
+            }
+            FormMethod::Post => FormRequest {
+                method: FormMethod::Post,
+                url: self.action.clone(),
+                body: fields,
+            },
+        }
+    }
+}
+
+/// All forms on the page.
+pub fn discover(page: &ParsedPage) -> Vec<Form> {
+    select(page, "form")
+}
+
+/// Forms on the page matching a CSS selector, e.g. `form#search`.
+pub fn select(page: &ParsedPage, selector: &str) -> Vec<Form> {
+    let Ok(selector) = Selector::parse(selector) else {
+        return vec![];
+    };
+    page.document
+        .select(&selector)
+        .filter(|el| el.value().name() == "form")
+        .filter_map(|el| parse_form(el, &page.url))
+        .collect()
+}
+
+fn parse_form(form: ElementRef, base: &Url) -> Option<Form> {
+    let action = match form.value().attr("action").map(str::trim) {
+        Some(action) if !action.is_empty() => base.join(action).ok()?,
+        _ => base.clone(),
+    };
+    if !matches!(action.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let method = match form.value().attr("method") {
+        Some(m) if m.eq_ignore_ascii_case("post") => FormMethod::Post,
+        _ => FormMethod::Get,
+    };
+
+    let controls = Selector::parse("input, select, textarea").ok()?;
+    let option = Selector::parse("option").ok()?;
+
+    let mut fields = Vec::new();
+    for control in form.select(&controls) {
+        let el = control.value();
+        let Some(name) = el.attr("name").filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        if el.attr("disabled").is_some() {
+            continue;
+        }
+
+        let value = match el.name() {
+            "input" => {
+                let kind = el.attr("type").unwrap_or("text").to_ascii_lowercase();
+                match kind.as_str() {
+                    "submit" | "button" | "reset" | "image" | "file" => continue,
+                    "checkbox" | "radio" if el.attr("checked").is_none() => continue,
+                    "checkbox" | "radio" => el.attr("value").unwrap_or("on").to_string(),
+                    _ => el.attr("value").unwrap_or("").to_string(),
+                }
+            }
+            "select" => {
+                let options: Vec<ElementRef> = control.select(&option).collect();
+                let Some(chosen) = options
+                    .iter()
+                    .find(|o| o.value().attr("selected").is_some())
+                    .or(options.first())
+                else {
+                    continue;
+                };
+                chosen
+                    .value()
+                    .attr("value")
+                    .map(str::to_string)
+                    .unwrap_or_else(|| chosen.text().collect::<String>().trim().to_string())
+            }
+            _ => control.text().collect(),
+        };
+        fields.push((name.to_string(), value));
+    }
+
+    Some(Form {
+        action,
+        method,
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::{Browser, PageContent};
+    use crate::config::ContentKind;
+
+    fn page(html: &str) -> ParsedPage {
+        Browser::parse(&PageContent {
+            url: Url::parse("https://example.com/docs/index.html").unwrap(),
+            status: 200,
+            kind: ContentKind::Html,
+            body: html.to_string(),
+            retry_after: None,
+        })
+    }
+
+    const FORMS: &str = r#"
+        <form id="search" action="/search?old=1">
+            <input name="q" value="default">
+            <input type="hidden" name="lang" value="en">
+            <input type="checkbox" name="exact">
+            <select name="sort"><option value="date">Date</option>
+                <option selected>Relevance</option></select>
+            <input type="submit" name="go" value="Search">
+        </form>
+        <form id="login" method="POST" action="login">
+            <input name="user"><input type="password" name="pass">
+            <textarea name="note">hi</textarea>
+            <input name="token" value="x" disabled>
+        </form>
+    "#;
+
+    #[test]
+    fn test_discover_forms() {
+        let forms = discover(&page(FORMS));
+        assert_eq!(forms.len(), 2);
+
+        assert_eq!(forms[0].method, FormMethod::Get);
+        assert_eq!(forms[0].action.as_str(), "https://example.com/search?old=1");
+        assert_eq!(
+            forms[0].fields,
+            vec![
+                ("q".to_string(), "default".to_string()),
+                ("lang".to_string(), "en".to_string()),
+                ("sort".to_string(), "Relevance".to_string()),
+            ]
+        );
+
+        assert_eq!(forms[1].method, FormMethod::Post);
+        assert_eq!(forms[1].action.as_str(), "https://example.com/docs/login");
+        assert_eq!(forms[1].fields.len(), 3);
+    }
+
+    #[test]
+    fn test_fill_forms() {
+        let page = page(FORMS);
+        let values = HashMap::from([
+            ("q".to_string(), "rust async".to_string()),
+            ("page".to_string(), "2".to_string()),
+        ]);
+
+        let search = select(&page, "form#search")[0].fill(&values);
+        assert_eq!(
+            search.url.as_str(),
+            "https://example.com/search?q=rust+async&lang=en&sort=Relevance&page=2"
+        );
+        assert!(search.body.is_empty());
+
+        let login = select(&page, "#login")[0].fill(&HashMap::new());
+        assert_eq!(login.url.as_str(), "https://example.com/docs/login");
+        assert_eq!(login.body[2], ("note".to_string(), "hi".to_string()));
+        assert_ne!(search.key(), login.key());
+    }
+}
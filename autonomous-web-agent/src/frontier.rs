@@ -0,0 +1,260 @@
+use crate::config::{FrontierConfig, Strategy};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use url::Url;
+
+/// A URL being considered for the frontier.
+pub struct LinkCandidate<'a> {
+    pub url: &'a Url,
+    pub depth: usize,
+    /// Anchor text of the link, if it was found on a page.
+    pub anchor_text: Option<&'a str>,
+    /// The page the link was found on; `None` for seeds and sitemap URLs.
+    pub source: Option<&'a Url>,
+}
+
+/// Ranks frontier candidates for the best-first strategy; higher scores
+/// are visited first.
+pub trait Scorer: Send {
+    fn score(&self, candidate: &LinkCandidate) -> f64;
+}
+
+/// The default scorer: a weighted sum of depth, follow-pattern match
+/// strength, keyword relevance and same-host preference.
+pub struct WeightedScorer {
+    config: FrontierConfig,
+    keywords: Vec<String>,
+    follow_patterns: Vec<Regex>,
+}
+
+impl WeightedScorer {
+    pub fn new(config: FrontierConfig, follow_patterns: Vec<Regex>) -> Self {
+        let keywords = config.keywords.iter().map(|k| k.to_lowercase()).collect();
+        Self {
+            config,
+            keywords,
+            follow_patterns,
+        }
+    }
+
+    /// The fraction of the URL covered by the best follow-pattern match.
+    fn pattern_strength(&self, url: &str) -> f64 {
+        self.follow_patterns
+            .iter()
+            .filter_map(|p| p.find(url))
+            .map(|m| m.len() as f64 / url.len().max(1) as f64)
+            .fold(0.0, f64::max)
+    }
+
+    /// The fraction of keywords found in the anchor text or URL.
+    fn keyword_relevance(&self, candidate: &LinkCandidate) -> f64 {
+        if self.keywords.is_empty() {
+            return 0.0;
+        }
+        let haystack = format!(
+            "{} {}",
+            candidate.anchor_text.unwrap_or(""),
+            candidate.url.path()
+        )
+        .to_lowercase();
+        let matched = self
+            .keywords
+            .iter()
+            .filter(|k| haystack.contains(k.as_str()))
+            .count();
+        matched as f64 / self.keywords.len() as f64
+    }
+}
+
+impl Scorer for WeightedScorer {
+    fn score(&self, candidate: &LinkCandidate) -> f64 {
+        let same_host = candidate
+            .source
+            .is_some_and(|s| s.host_str() == candidate.url.host_str());
+
+        -self.config.depth_weight * candidate.depth as f64
+            + self.config.pattern_weight * self.pattern_strength(candidate.url.as_str())
+            + self.config.keyword_weight * self.keyword_relevance(candidate)
+            + if same_host { self.config.same_host_weight } else { 0.0 }
+    }
+}
+
+/// A URL waiting in the frontier.
+#[derive(Debug, Clone)]
+pub struct QueuedVisit {
+    pub url: Url,
+    pub depth: usize,
+    pub score: f64,
+}
+
+struct Entry {
+    priority: f64,
+    /// Tie-breaker: insertion order, negated for FIFO strategies.
+    order: i64,
+    visit: QueuedVisit,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .total_cmp(&other.priority)
+            .then(self.order.cmp(&other.order))
+    }
+}
+
+/// The set of URLs waiting to be visited, ordered by `Strategy`.
+pub struct Frontier {
+    strategy: Strategy,
+    entries: BinaryHeap<Entry>,
+    next_seq: i64,
+}
+
+impl Frontier {
+    pub fn new(strategy: Strategy) -> Self {
+        Self {
+            strategy,
+            entries: BinaryHeap::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, visit: QueuedVisit) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.push_with_seq(visit, seq);
+    }
+
+    fn push_with_seq(&mut self, visit: QueuedVisit, seq: i64) {
+        let (priority, order) = match self.strategy {
+            Strategy::Bfs => (0.0, -seq),
+            Strategy::Dfs => (0.0, seq),
+            Strategy::BestFirst => (visit.score, -seq),
+        };
+        self.entries.push(Entry {
+            priority,
+            order,
+            visit,
+        });
+    }
+
+    pub fn pop(&mut self) -> Option<QueuedVisit> {
+        self.entries.pop().map(|e| e.visit)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The queued visits in the order they would be popped.
+    pub fn to_vec(&self) -> Vec<QueuedVisit> {
+        let mut entries: Vec<&Entry> = self.entries.iter().collect();
+        entries.sort_by(|a, b| b.cmp(a));
+        entries.into_iter().map(|e| e.visit.clone()).collect()
+    }
+
+    /// Replace the frontier with `visits`, which keep their given order
+    /// relative to each other (as returned by `to_vec`).
+    pub fn restore(&mut self, visits: Vec<QueuedVisit>) {
+        self.entries.clear();
+        let n = visits.len() as i64;
+        for (i, visit) in visits.into_iter().enumerate() {
+            let i = i as i64;
+            // Depth-first pops the highest sequence number first.
+            let seq = if self.strategy == Strategy::Dfs { n - 1 - i } else { i };
+            self.push_with_seq(visit, seq);
+        }
+        self.next_seq = n;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visit(path: &str, score: f64) -> QueuedVisit {
+        QueuedVisit {
+            url: Url::parse("https://example.com").unwrap().join(path).unwrap(),
+            depth: 0,
+            score,
+        }
+    }
+
+    fn drain(frontier: &mut Frontier) -> Vec<String> {
+        std::iter::from_fn(|| frontier.pop())
+            .map(|v| v.url.path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_strategies_order() {
+        for (strategy, expected) in [
+            (Strategy::Bfs, ["/a", "/b", "/c"]),
+            (Strategy::Dfs, ["/c", "/b", "/a"]),
+            (Strategy::BestFirst, ["/b", "/a", "/c"]),
+        ] {
+            let mut frontier = Frontier::new(strategy);
+            frontier.push(visit("/a", 1.0));
+            frontier.push(visit("/b", 2.0));
+            frontier.push(visit("/c", 1.0));
+
+            let order = frontier.to_vec();
+            assert_eq!(drain(&mut frontier), expected, "{:?}", strategy);
+
+            // Restoring keeps the order, and later pushes still follow
+            // the strategy.
+            frontier.restore(order);
+            frontier.push(visit("/d", 1.5));
+            let restored = drain(&mut frontier);
+            let d = restored.iter().position(|p| p == "/d").unwrap();
+            match strategy {
+                Strategy::Bfs => assert_eq!(d, 3),
+                Strategy::Dfs => assert_eq!(d, 0),
+                Strategy::BestFirst => assert_eq!(d, 1),
+            }
+        }
+    }
+
+    #[test]
+    fn test_weighted_scorer() {
+        let config = FrontierConfig {
+            keywords: vec!["Pricing".to_string()],
+            ..Default::default()
+        };
+        let scorer = WeightedScorer::new(config, vec![Regex::new("/docs/").unwrap()]);
+        let source = Url::parse("https://example.com/").unwrap();
+        let score = |url: &str, depth: usize, anchor: Option<&str>| {
+            let url = Url::parse(url).unwrap();
+            scorer.score(&LinkCandidate {
+                url: &url,
+                depth,
+                anchor_text: anchor,
+                source: Some(&source),
+            })
+        };
+
+        let plain = score("https://example.com/about", 1, Some("About us"));
+        assert!(score("https://example.com/about", 2, Some("About us")) < plain);
+        assert!(score("https://other.example/about", 1, Some("About us")) < plain);
+        assert!(score("https://example.com/docs/", 1, None) > plain);
+        assert!(score("https://example.com/plans", 1, Some("See pricing")) > plain);
+    }
+}
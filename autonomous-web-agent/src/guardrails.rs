@@ -1,64 +1,271 @@
-use crate::config::SafetyConfig;
+use crate::config::{RuleAction, SafetyConfig};
 use crate::error::{AgentError, Result};
+use crate::forms::{FormMethod, FormRequest};
+use crate::policy::{domain_matches, Policy, PolicyDecision};
+use crate::robots::RobotsTxt;
+use crate::state::CrawlState;
 use regex::Regex;
-use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
 use url::Url;
 
+/// A budget whose exhaustion stops the crawl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Budget {
+    Requests,
+    Time,
+    Bytes,
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Budget::Requests => write!(f, "request"),
+            Budget::Time => write!(f, "time"),
+            Budget::Bytes => write!(f, "byte"),
+        }
+    }
+}
+
+/// What a crawl has spent of its byte, per-domain and per-path budgets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub bytes_downloaded: u64,
+    /// Requests made to each host.
+    pub domain_requests: HashMap<String, usize>,
+    /// Pages requested under each `max_pages_per_path` prefix.
+    pub path_pages: HashMap<String, usize>,
+}
+
+/// Politeness bookkeeping for a single host.
+#[derive(Default)]
+struct HostState {
+    in_flight: usize,
+    next_slot: Option<Instant>,
+}
+
 pub struct Guardrails {
     config: SafetyConfig,
+    policy: Policy,
+    user_agent: String,
     request_count: usize,
-    last_request_time: Option<Instant>,
+    usage: BudgetUsage,
+    /// When the current run started, for the time budget.
+    started: Option<Instant>,
+    exhausted: Option<(Budget, String)>,
+    hosts: HashMap<String, HostState>,
+    /// Parsed robots.txt per origin (`scheme://host:port`).
+    robots: HashMap<String, RobotsTxt>,
     blocked_patterns: Vec<Regex>,
+    allowed_form_actions: Vec<Regex>,
     pub safety_blocks: Vec<String>,
+    /// URLs the policy denied, with the rule that denied them.
+    pub policy_denials: Vec<PolicyDecision>,
 }
 
 impl Guardrails {
-    pub fn new(config: SafetyConfig) -> Self {
+    pub fn new(config: SafetyConfig, user_agent: &str) -> Result<Self> {
         let blocked_patterns = config
             .blocked_content_patterns
             .iter()
             .filter_map(|p| Regex::new(p).ok())
             .collect();
+        let allowed_form_actions = config
+            .allowed_form_actions
+            .iter()
+            .filter_map(|p| Regex::new(p).ok())
+            .collect();
 
-        Self {
+        Ok(Self {
+            policy: Policy::new(&config)?,
             config,
+            user_agent: user_agent.to_string(),
             request_count: 0,
-            last_request_time: None,
+            usage: BudgetUsage::default(),
+            started: None,
+            exhausted: None,
+            hosts: HashMap::new(),
+            robots: HashMap::new(),
             blocked_patterns,
+            allowed_form_actions,
             safety_blocks: Vec::new(),
+            policy_denials: Vec::new(),
+        })
+    }
+
+    /// Check a URL against the policy. `addrs` are the addresses its host
+    /// resolves to, if `needs_addresses`. Denials are logged.
+    pub fn check_url(&mut self, url: &Url, addrs: &[IpAddr]) -> PolicyDecision {
+        let decision = self.policy.evaluate(url, addrs);
+        if decision.action == RuleAction::Deny {
+            self.safety_blocks.push(decision.reason.clone());
+            self.policy_denials.push(decision.clone());
         }
+        decision
     }
 
-    pub fn check_url(&mut self, url: &Url) -> Result<()> {
-        let domain = url.host_str().unwrap_or("");
+    /// Whether `check_url` needs the addresses a URL's host resolves to.
+    pub fn needs_addresses(&self) -> bool {
+        self.policy.needs_addresses()
+    }
 
-        if self.config.allowed_domains.is_empty() {
-            return Ok(());
+    /// If robots.txt must be consulted for this URL and has not been
+    /// fetched yet, return the robots.txt URL to fetch.
+    pub fn robots_url_needed(&self, url: &Url) -> Option<Url> {
+        if !self.robots_applies(url) || self.robots.contains_key(&origin_key(url)) {
+            return None;
         }
+        url.join("/robots.txt").ok()
+    }
 
-        let allowed = self.config.allowed_domains.iter().any(|d| {
-            domain == d.as_str() || domain.ends_with(&format!(".{}", d))
-        });
+    /// Cache the robots.txt for the URL's origin.
+    pub fn store_robots(&mut self, url: &Url, robots: RobotsTxt) {
+        self.robots.insert(origin_key(url), robots);
+    }
+
+    /// The cached robots.txt for the URL's origin, if fetched.
+    pub fn robots_for(&self, url: &Url) -> Option<&RobotsTxt> {
+        self.robots.get(&origin_key(url))
+    }
 
-        if !allowed {
-            let msg = format!("Domain '{}' not in allowlist", domain);
+    /// Reject URLs disallowed by their site's robots.txt for our user agent.
+    ///
+    /// Origins without a cached robots.txt and domains listed in
+    /// `robots_exempt_domains` are allowed.
+    pub fn check_robots(&mut self, url: &Url) -> Result<()> {
+        if !self.robots_applies(url) {
+            return Ok(());
+        }
+        let Some(robots) = self.robots.get(&origin_key(url)) else {
+            return Ok(());
+        };
+
+        let path = match url.query() {
+            Some(q) => format!("{}?{}", url.path(), q),
+            None => url.path().to_string(),
+        };
+        if !robots.is_allowed(&self.user_agent, &path) {
+            let msg = format!("Disallowed by robots.txt: {}", url);
             self.safety_blocks.push(msg.clone());
             return Err(AgentError::SafetyViolation(msg));
         }
-
         Ok(())
     }
 
+    fn robots_applies(&self, url: &Url) -> bool {
+        let domain = url.host_str().unwrap_or("");
+        !domain_matches(domain, &self.config.robots_exempt_domains)
+    }
+
+    /// Start the clock for the time budget.
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    /// Fail once the request, time or byte budget is spent. The first
+    /// budget to run out is logged and reported by `exhausted_budget`;
+    /// later calls fail without logging it again.
     pub fn check_budget(&mut self) -> Result<()> {
-        if self.request_count >= self.config.max_requests {
-            let msg = format!(
-                "Request budget exhausted ({}/{})",
-                self.request_count, self.config.max_requests
-            );
-            self.safety_blocks.push(msg.clone());
-            return Err(AgentError::BudgetExhausted(msg));
+        if let Some((_, msg)) = &self.exhausted {
+            return Err(AgentError::BudgetExhausted(msg.clone()));
         }
-        Ok(())
+
+        let elapsed = self.started.map(|s| s.elapsed()).unwrap_or_default();
+        let bytes = self.usage.bytes_downloaded;
+        let exhausted = if self.request_count >= self.config.max_requests {
+            Some((
+                Budget::Requests,
+                format!(
+                    "Request budget exhausted ({}/{})",
+                    self.request_count, self.config.max_requests
+                ),
+            ))
+        } else if let Some(max) = self
+            .config
+            .max_duration_secs
+            .filter(|max| elapsed >= Duration::from_secs(*max))
+        {
+            Some((
+                Budget::Time,
+                format!(
+                    "Time budget exhausted ({:.1}s/{}s)",
+                    elapsed.as_secs_f64(),
+                    max
+                ),
+            ))
+        } else {
+            self.config
+                .max_total_bytes
+                .filter(|max| bytes >= *max)
+                .map(|max| {
+                    (
+                        Budget::Bytes,
+                        format!("Byte budget exhausted ({}/{} bytes)", bytes, max),
+                    )
+                })
+        };
+
+        match exhausted {
+            Some((budget, msg)) => {
+                self.safety_blocks.push(msg.clone());
+                self.exhausted = Some((budget, msg.clone()));
+                Err(AgentError::BudgetExhausted(msg))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The budget that stopped the crawl, if one did.
+    pub fn exhausted_budget(&self) -> Option<Budget> {
+        self.exhausted.as_ref().map(|(budget, _)| *budget)
+    }
+
+    /// Reject a URL whose host has used up `max_requests_per_domain` or,
+    /// unless it is a retry, whose path falls under a full
+    /// `max_pages_per_path` prefix.
+    pub fn check_quota(&mut self, url: &Url, retry: bool) -> Result<()> {
+        let host = url.host_str().unwrap_or("");
+        let mut msg = None;
+        if let Some(max) = self.config.max_requests_per_domain {
+            let used = self.usage.domain_requests.get(host).copied().unwrap_or(0);
+            if used >= max {
+                msg = Some(format!("Request cap for {} reached ({}/{})", host, used, max));
+            }
+        }
+        if !retry && msg.is_none() {
+            msg = self
+                .config
+                .max_pages_per_path
+                .iter()
+                .filter(|(prefix, _)| url.path().starts_with(prefix.as_str()))
+                .find_map(|(prefix, max)| {
+                    let used = self.usage.path_pages.get(prefix).copied().unwrap_or(0);
+                    (used >= *max).then(|| {
+                        format!("Page cap for {} reached ({}/{}): {}", prefix, used, max, url)
+                    })
+                });
+        }
+
+        match msg {
+            Some(msg) => {
+                self.safety_blocks.push(msg.clone());
+                Err(AgentError::BudgetExhausted(msg))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the URL's host has requests left under
+    /// `max_requests_per_domain`.
+    pub fn domain_has_quota(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or("");
+        self.config
+            .max_requests_per_domain
+            .is_none_or(|max| self.usage.domain_requests.get(host).copied().unwrap_or(0) < max)
     }
 
     pub fn check_depth(&mut self, depth: usize) -> Result<()> {
@@ -70,6 +277,25 @@ impl Guardrails {
         Ok(())
     }
 
+    /// Reject form submissions whose action URL is not explicitly
+    /// allowlisted in `allowed_form_actions`.
+    pub fn check_form(&mut self, form: &FormRequest) -> Result<()> {
+        let mut action = form.url.clone();
+        if form.method == FormMethod::Get {
+            action.set_query(None);
+        }
+        if !self
+            .allowed_form_actions
+            .iter()
+            .any(|p| p.is_match(action.as_str()))
+        {
+            let msg = format!("Form submission not allowlisted: {} {}", form.method, action);
+            self.safety_blocks.push(msg.clone());
+            return Err(AgentError::SafetyViolation(msg));
+        }
+        Ok(())
+    }
+
     pub fn check_content(&mut self, body: &str) -> Result<()> {
         for pattern in &self.blocked_patterns {
             if pattern.is_match(body) {
@@ -81,22 +307,170 @@ impl Guardrails {
         Ok(())
     }
 
-    pub async fn enforce_rate_limit(&mut self) {
-        if let Some(last) = self.last_request_time {
-            let elapsed = last.elapsed().as_millis() as u64;
-            if elapsed < self.config.rate_limit_ms {
-                let delay = self.config.rate_limit_ms - elapsed;
-                tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+    /// Whether another connection to the URL's host may be opened.
+    pub fn host_has_capacity(&self, url: &Url) -> bool {
+        self.hosts
+            .get(&host_key(url))
+            .is_none_or(|h| h.in_flight < self.max_connections_per_host())
+    }
+
+    /// Reserve a connection slot on the URL's host.
+    ///
+    /// Returns `None` if the host is at its connection cap. Otherwise
+    /// returns how long to wait before sending the request so that request
+    /// starts to the host are at least `rate_limit_ms` (or the site's
+    /// robots.txt `Crawl-delay`, if longer, up to `max_crawl_delay_ms`)
+    /// apart. Every reservation must be paired with `release_host`.
+    pub fn reserve_host_slot(&mut self, url: &Url) -> Option<Duration> {
+        let max_connections = self.max_connections_per_host();
+        let mut rate_limit = Duration::from_millis(self.config.rate_limit_ms);
+        let max_crawl_delay = Duration::from_millis(self.config.max_crawl_delay_ms);
+        if self.robots_applies(url) {
+            if let Some(delay) = self
+                .robots
+                .get(&origin_key(url))
+                .and_then(|r| r.crawl_delay(&self.user_agent, max_crawl_delay))
+            {
+                rate_limit = rate_limit.max(delay);
             }
         }
+        let state = self.hosts.entry(host_key(url)).or_default();
+
+        if state.in_flight >= max_connections {
+            return None;
+        }
+        state.in_flight += 1;
+
+        let now = Instant::now();
+        let start = match state.next_slot {
+            Some(slot) if slot > now => slot,
+            _ => now,
+        };
+        state.next_slot = Some(start + rate_limit);
+        Some(start - now)
+    }
+
+    /// Release a connection slot reserved with `reserve_host_slot`.
+    pub fn release_host(&mut self, url: &Url) {
+        if let Some(state) = self.hosts.get_mut(&host_key(url)) {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
     }
 
-    pub fn record_request(&mut self) {
+    fn max_connections_per_host(&self) -> usize {
+        self.config.max_connections_per_host.max(1)
+    }
+
+    pub fn record_request(&mut self, url: &Url) {
         self.request_count += 1;
-        self.last_request_time = Some(Instant::now());
+        let host = url.host_str().unwrap_or("").to_string();
+        *self.usage.domain_requests.entry(host).or_default() += 1;
+    }
+
+    /// Count a new page (not a retry) against the path prefixes it is under.
+    pub fn record_page(&mut self, url: &Url) {
+        for prefix in self.config.max_pages_per_path.keys() {
+            if url.path().starts_with(prefix.as_str()) {
+                *self.usage.path_pages.entry(prefix.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn record_bytes(&mut self, bytes: u64) {
+        self.usage.bytes_downloaded += bytes;
+    }
+
+    /// Restore the request count, budget usage and safety log of a saved
+    /// crawl. The configured budgets still apply, so a resumed crawl can be
+    /// given larger ones; the time budget starts afresh.
+    pub fn restore(&mut self, state: &mut CrawlState) {
+        self.request_count = state.requests_made;
+        self.usage = std::mem::take(&mut state.usage);
+        self.safety_blocks = std::mem::take(&mut state.safety_blocks);
+        self.policy_denials = std::mem::take(&mut state.policy_denials);
+    }
+
+    pub fn usage(&self) -> &BudgetUsage {
+        &self.usage
     }
 
     pub fn requests_made(&self) -> usize {
         self.request_count
     }
+
+    pub fn requests_remaining(&self) -> usize {
+        self.config.max_requests.saturating_sub(self.request_count)
+    }
+}
+
+fn origin_key(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+fn host_key(url: &Url) -> String {
+    match url.port_or_known_default() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or(""), port),
+        None => url.host_str().unwrap_or("").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_byte_and_quota_budgets() {
+        let config = SafetyConfig {
+            max_total_bytes: Some(1000),
+            max_requests_per_domain: Some(2),
+            max_pages_per_path: HashMap::from([("/blog/".to_string(), 1)]),
+            ..Default::default()
+        };
+        let mut guardrails = Guardrails::new(config, "test").unwrap();
+        guardrails.start();
+
+        let post = url("https://a.test/blog/1");
+        assert!(guardrails.check_quota(&post, false).is_ok());
+        guardrails.record_request(&post);
+        guardrails.record_page(&post);
+        // The prefix is full for new pages, but a retry may go ahead.
+        assert!(guardrails.check_quota(&url("https://a.test/blog/2"), false).is_err());
+        assert!(guardrails.check_quota(&post, true).is_ok());
+
+        guardrails.record_request(&url("https://a.test/about"));
+        assert!(guardrails.check_quota(&url("https://a.test/"), false).is_err());
+        assert!(guardrails.check_quota(&url("https://b.test/"), false).is_ok());
+
+        guardrails.record_bytes(600);
+        assert!(guardrails.check_budget().is_ok());
+        guardrails.record_bytes(600);
+        assert!(guardrails.check_budget().is_err());
+        assert_eq!(guardrails.exhausted_budget(), Some(Budget::Bytes));
+        // Exhaustion is logged once.
+        assert!(guardrails.check_budget().is_err());
+        assert_eq!(
+            guardrails
+                .safety_blocks
+                .iter()
+                .filter(|b| b.starts_with("Byte budget"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let config = SafetyConfig {
+            max_duration_secs: Some(0),
+            ..Default::default()
+        };
+        let mut guardrails = Guardrails::new(config, "test").unwrap();
+        guardrails.start();
+        assert!(guardrails.check_budget().is_err());
+        assert_eq!(guardrails.exhausted_budget(), Some(Budget::Time));
+    }
 }
@@ -1,8 +1,27 @@
+pub mod agent;
+pub mod article;
 pub mod browser;
+pub mod cache;
 pub mod config;
+pub mod content;
+pub mod dedup;
 pub mod error;
 pub mod extractor;
+pub mod forms;
+pub mod frontier;
 pub mod guardrails;
+pub mod metadata;
+pub mod output;
+pub mod pagination;
 pub mod planner;
+pub mod policy;
+pub mod provenance;
+pub mod redact;
 pub mod report;
+pub mod retry;
+pub mod robots;
+pub mod sitemap;
+pub mod state;
 pub mod task;
+pub mod taskfile;
+pub mod transform;
@@ -1,29 +1,121 @@
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
 use regex::Regex;
 use std::collections::HashMap;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use url::Url;
 
-use autonomous_web_agent::browser::Browser;
-use autonomous_web_agent::config::{AgentConfig, SafetyConfig};
-
-use autonomous_web_agent::guardrails::Guardrails;
-use autonomous_web_agent::planner::Planner;
+use autonomous_web_agent::agent::{Agent, AgentEvent};
+use autonomous_web_agent::config::{
+    AgentConfig, CacheConfig, ContentConfig, ContentKind, DedupConfig, FrontierConfig,
+    PolicyConfig, RedactionAction, RedactionConfig, RetryConfig, SafetyConfig, Strategy,
+};
+use autonomous_web_agent::output::{self, OutputFormat, OutputWriter};
 use autonomous_web_agent::report::Report;
-use autonomous_web_agent::task::{AgentAction, ExtractionGoal, Task};
+use autonomous_web_agent::state::CrawlState;
+use autonomous_web_agent::task::{
+    ExtractionGoal, FieldSpec, FormSubmission, Pagination, Task,
+};
+use autonomous_web_agent::taskfile;
+use autonomous_web_agent::transform::Pattern;
 
 #[derive(Debug, Clone, ValueEnum)]
 enum GoalType {
     Links,
     Text,
     Structured,
+    Records,
+    Article,
+    Metadata,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum StrategyArg {
+    Bfs,
+    Dfs,
+    BestFirst,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ContentArg {
+    Text,
+    Json,
+    Xml,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum RedactionArg {
+    Mask,
+    Drop,
+    Keep,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputArg {
+    Json,
+    Jsonl,
+    Csv,
+    Sqlite,
+}
+
+impl From<OutputArg> for OutputFormat {
+    fn from(arg: OutputArg) -> Self {
+        match arg {
+            OutputArg::Json => OutputFormat::Json,
+            OutputArg::Jsonl => OutputFormat::Jsonl,
+            OutputArg::Csv => OutputFormat::Csv,
+            OutputArg::Sqlite => OutputFormat::Sqlite,
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
 #[command(name = "autonomous-web-agent")]
 #[command(about = "An autonomous web agent with safety guardrails")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    crawl: CrawlArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the tasks defined in a YAML, TOML or JSON task file
+    Run {
+        /// Path to the task file
+        taskfile: PathBuf,
+
+        /// Output results as a JSON array of reports
+        #[arg(long)]
+        json: bool,
+
+        /// Write results in this format (JSON Lines and CSV are written as
+        /// items are extracted)
+        #[arg(long, value_enum, conflicts_with = "json")]
+        output: Option<OutputArg>,
+
+        /// File to write --output to instead of stdout (required for sqlite)
+        #[arg(long, requires = "output")]
+        output_file: Option<PathBuf>,
+
+        /// Directory for per-task crawl state files; tasks resume from
+        /// their state file if one exists
+        #[arg(long)]
+        state_dir: Option<PathBuf>,
+    },
+    /// Summarize a saved crawl state file
+    Inspect {
+        /// Path to the state file
+        state: PathBuf,
+    },
+}
+
+#[derive(Args, Debug)]
+struct CrawlArgs {
     /// Seed URL to start browsing from
     #[arg(short, long)]
     url: Option<String>,
@@ -40,26 +132,177 @@ struct Cli {
     #[arg(short = 'R', long, default_value = "15")]
     max_requests: usize,
 
-    /// Rate limit in milliseconds between requests
+    /// Stop the crawl after this many seconds
+    #[arg(long)]
+    max_duration: Option<u64>,
+
+    /// Stop the crawl after receiving this many bytes of response bodies
+    #[arg(long)]
+    max_bytes: Option<u64>,
+
+    /// Maximum number of requests to any single host
+    #[arg(long)]
+    max_requests_per_domain: Option<usize>,
+
+    /// Maximum pages under a path prefix, as prefix=count (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    max_pages_per_path: Vec<String>,
+
+    /// Rate limit in milliseconds between requests to the same host
     #[arg(long, default_value = "500")]
     rate_limit: u64,
 
+    /// Longest robots.txt Crawl-delay honored, in milliseconds
+    #[arg(long, default_value = "30000")]
+    max_crawl_delay: u64,
+
+    /// Maximum number of concurrent fetches across all hosts
+    #[arg(long, default_value = "4")]
+    concurrency: usize,
+
+    /// Maximum concurrent connections to a single host
+    #[arg(long, default_value = "2")]
+    per_host_connections: usize,
+
+    /// Domains whose robots.txt should be ignored (comma-separated).
+    /// Only use for sites you own.
+    #[arg(long, value_delimiter = ',')]
+    ignore_robots_for: Vec<String>,
+
+    /// Private or loopback address ranges that may be crawled
+    /// (comma-separated CIDRs, e.g. 127.0.0.1/32). Non-public addresses
+    /// are refused otherwise.
+    #[arg(long, value_delimiter = ',')]
+    allow_private_range: Vec<String>,
+
     /// Extraction goal type
     #[arg(short, long, value_enum, default_value = "text")]
     goal: GoalType,
 
-    /// CSS selectors for extraction (comma-separated)
+    /// CSS selectors for extraction (comma-separated). For `structured`,
+    /// use name=selector (no transforms); for `records`, name=selector[@attribute][!]
+    /// with `!` marking a required field, optionally followed by
+    /// transforms such as |ws, |number, |currency, |date, |url or
+    /// |regex:<pattern>.
     #[arg(short, long, value_delimiter = ',')]
     selectors: Vec<String>,
 
+    /// CSS selector for the repeated container element of each record
+    #[arg(long, default_value = "body")]
+    container: String,
+
+    /// For `metadata`, schema.org types to keep, e.g. Product,Article
+    /// (comma-separated). If empty, all items are kept.
+    #[arg(long, value_delimiter = ',')]
+    schema_types: Vec<String>,
+
     /// Regex patterns for links to follow (comma-separated)
     #[arg(short, long, value_delimiter = ',')]
     patterns: Vec<String>,
 
+    /// Regex patterns for links never to follow (comma-separated)
+    #[arg(short = 'x', long, value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Order in which discovered URLs are visited
+    #[arg(long, value_enum, default_value = "bfs")]
+    strategy: StrategyArg,
+
+    /// Keywords that prioritize links whose anchor text or URL contains
+    /// them, with --strategy best-first (comma-separated)
+    #[arg(short, long, value_delimiter = ',')]
+    keywords: Vec<String>,
+
+    /// CSS selector of a form to fill in and submit wherever it is found
+    #[arg(long)]
+    form: Option<String>,
+
+    /// Values for the form's fields as name=value (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    form_values: Vec<String>,
+
+    /// Regex patterns for form action URLs that may be submitted
+    /// (comma-separated). Forms are never submitted otherwise.
+    #[arg(long, value_delimiter = ',')]
+    allow_form_action: Vec<String>,
+
+    /// Follow rel="next" pagination links
+    #[arg(long)]
+    paginate: bool,
+
+    /// CSS selector for "next page" links (implies --paginate)
+    #[arg(long)]
+    next_selector: Option<String>,
+
+    /// Pagination URL template with a {page} placeholder (implies --paginate)
+    #[arg(long, requires = "page_pattern")]
+    page_template: Option<String>,
+
+    /// Regex for the URLs of pages that start a paginated series
+    /// (default: the seed page only)
+    #[arg(long)]
+    page_pattern: Option<Regex>,
+
+    /// Maximum pages per paginated series
+    #[arg(long, default_value = "10")]
+    max_pages: usize,
+
+    /// Seed the crawl from the site's sitemaps
+    #[arg(long)]
+    sitemaps: bool,
+
+    /// Skip pages whose text SimHash is within this many bits of an
+    /// earlier page's (e.g. 3)
+    #[arg(long, value_name = "BITS")]
+    skip_near_duplicates: Option<u32>,
+
+    /// Retries for timeouts, connection failures, 429 and 5xx responses (never for form POSTs)
+    #[arg(long, default_value = "2")]
+    max_retries: u32,
+
+    /// Skip responses with bodies larger than this many bytes
+    #[arg(long, default_value = "10485760")]
+    max_body_size: u64,
+
+    /// Also extract text from these non-HTML responses (comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    extract_content: Vec<ContentArg>,
+
+    /// What to do with emails, phone numbers, card numbers and API tokens
+    /// in extracted values: mask them, drop the items holding them, or
+    /// keep them
+    #[arg(long, value_enum, default_value = "mask")]
+    redaction: RedactionArg,
+
+    /// Cache HTTP responses in this directory, revalidating stale entries
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Replay the crawl entirely from --cache-dir without network access
+    #[arg(long, requires = "cache_dir")]
+    offline: bool,
+
+    /// Save crawl progress to this file, resuming from it if it exists
+    #[arg(long)]
+    state: Option<PathBuf>,
+
     /// Output results as JSON
     #[arg(long)]
     json: bool,
 
+    /// Write results in this format (JSON Lines and CSV are written as
+    /// items are extracted)
+    #[arg(long, value_enum, conflicts_with = "json")]
+    output: Option<OutputArg>,
+
+    /// File to write --output to instead of stdout (required for sqlite)
+    #[arg(long, requires = "output")]
+    output_file: Option<PathBuf>,
+
+    /// Write the crawl's link graph to this .dot or .graphml file
+    #[arg(long)]
+    graph: Option<PathBuf>,
+
     /// Run built-in demo mode
     #[arg(long)]
     demo: bool,
@@ -69,9 +312,113 @@ struct Cli {
 async fn main() {
     let cli = Cli::parse();
 
-    println!("{}", "🤖 Autonomous Web Agent".cyan().bold());
-    println!("{}", "━━━━━━━━━━━━━━━━━━━━━━━".cyan());
+    eprintln!("{}", "🤖 Autonomous Web Agent".cyan().bold());
+    eprintln!("{}", "━━━━━━━━━━━━━━━━━━━━━━━".cyan());
+
+    match cli.command {
+        Some(Command::Run {
+            taskfile,
+            json,
+            output,
+            output_file,
+            state_dir,
+        }) => {
+            let format = if json { Some(OutputFormat::Json) } else { output.map(Into::into) };
+            run_taskfile(taskfile, format, output_file, state_dir).await
+        }
+        Some(Command::Inspect { state }) => inspect_state(&state),
+        None => run_crawl(cli.crawl).await,
+    }
+}
+
+async fn run_taskfile(
+    path: PathBuf,
+    format: Option<OutputFormat>,
+    output_file: Option<PathBuf>,
+    state_dir: Option<PathBuf>,
+) {
+    let specs = match taskfile::load(&path) {
+        Ok(specs) => specs,
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    // Validate every task up front so a typo in the last task does not
+    // surface only after the earlier ones have crawled.
+    let mut tasks = Vec::new();
+    for spec in &specs {
+        match spec.to_task() {
+            Ok(task) => tasks.push((task, spec.agent_config())),
+            Err(e) => {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+    }
 
+    let writer = format.map(|format| open_output(format, output_file.as_deref(), true));
+    let total = tasks.len();
+    for (i, (task, config)) in tasks.into_iter().enumerate() {
+        eprintln!(
+            "\n{}",
+            format!("Task {}/{}: {}", i + 1, total, task.name).yellow()
+        );
+        let state = state_dir.as_ref().map(|dir| dir.join(state_file_name(&task.name)));
+        let report = run_agent(task, config, state, writer.clone()).await;
+        // Results written to stdout replace the summary.
+        if writer.is_none() || output_file.is_some() {
+            report.print_summary();
+        }
+    }
+
+    if let Some(writer) = writer {
+        close_output(writer);
+    }
+}
+
+/// A file name for a task's state file derived from the task name.
+fn state_file_name(task_name: &str) -> String {
+    let slug: String = task_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    format!("{}.json", slug.trim_matches('-'))
+}
+
+fn inspect_state(path: &Path) {
+    let state = match CrawlState::load(path) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("\n{}", format!("Crawl state: {}", state.task_name).cyan().bold());
+    println!("  Pages visited:   {}", state.pages_visited.to_string().green());
+    println!("  Pages skipped:   {}", state.pages_skipped.to_string().yellow());
+    println!("  Requests made:   {}", state.requests_made);
+    println!("  Bytes received:  {}", state.usage.bytes_downloaded);
+    println!("  Items extracted: {}", state.results.len().to_string().green());
+    println!("  Safety blocks:   {}", state.safety_blocks.len().to_string().red());
+    println!("  Frontier:        {} URLs", state.frontier.len());
+    for visit in state.frontier.iter().take(10) {
+        println!(
+            "    {} [depth={} score={:.2}] {}",
+            "▸".dimmed(),
+            visit.depth,
+            visit.score,
+            visit.url
+        );
+    }
+    if state.frontier.len() > 10 {
+        println!("    {} ... and {} more", "▸".dimmed(), state.frontier.len() - 10);
+    }
+}
+
+async fn run_crawl(cli: CrawlArgs) {
     if cli.demo {
         run_demo().await;
         return;
@@ -94,16 +441,84 @@ async fn main() {
         }
     };
 
+    let mut max_pages_per_path = HashMap::new();
+    for limit in &cli.max_pages_per_path {
+        match limit.split_once('=').map(|(p, n)| (p, n.parse::<usize>())) {
+            Some((prefix, Ok(max))) => {
+                max_pages_per_path.insert(prefix.to_string(), max);
+            }
+            _ => {
+                eprintln!(
+                    "{} --max-pages-per-path expects prefix=count, got '{}'",
+                    "Error:".red().bold(),
+                    limit
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
     let safety = SafetyConfig {
         allowed_domains: cli.domains,
         max_depth: cli.max_depth,
         max_requests: cli.max_requests,
+        max_duration_secs: cli.max_duration,
+        max_total_bytes: cli.max_bytes,
+        max_requests_per_domain: cli.max_requests_per_domain,
+        max_pages_per_path,
         rate_limit_ms: cli.rate_limit,
+        max_crawl_delay_ms: cli.max_crawl_delay,
+        max_connections_per_host: cli.per_host_connections,
         blocked_content_patterns: vec![],
+        robots_exempt_domains: cli.ignore_robots_for,
+        allowed_form_actions: cli.allow_form_action,
+        policy: PolicyConfig {
+            allowed_private_ranges: cli.allow_private_range,
+            ..Default::default()
+        },
     };
 
     let config = AgentConfig {
-        safety: safety.clone(),
+        safety,
+        dedup: DedupConfig {
+            near_duplicate_distance: cli.skip_near_duplicates,
+            ..Default::default()
+        },
+        frontier: FrontierConfig {
+            strategy: match cli.strategy {
+                StrategyArg::Bfs => Strategy::Bfs,
+                StrategyArg::Dfs => Strategy::Dfs,
+                StrategyArg::BestFirst => Strategy::BestFirst,
+            },
+            keywords: cli.keywords,
+            ..Default::default()
+        },
+        cache: CacheConfig {
+            dir: cli.cache_dir,
+            offline: cli.offline,
+        },
+        retry: RetryConfig {
+            max_retries: cli.max_retries,
+            ..Default::default()
+        },
+        content: ContentConfig {
+            max_body_bytes: cli.max_body_size,
+            extract: cli
+                .extract_content
+                .iter()
+                .map(|kind| match kind {
+                    ContentArg::Text => ContentKind::Text,
+                    ContentArg::Json => ContentKind::Json,
+                    ContentArg::Xml => ContentKind::Xml,
+                })
+                .collect(),
+        },
+        redaction: RedactionConfig::all(match cli.redaction {
+            RedactionArg::Mask => RedactionAction::Mask,
+            RedactionArg::Drop => RedactionAction::Drop,
+            RedactionArg::Keep => RedactionAction::Keep,
+        }),
+        max_concurrent_requests: cli.concurrency,
         ..Default::default()
     };
 
@@ -137,6 +552,29 @@ async fn main() {
                 .collect();
             ExtractionGoal::ExtractStructured { fields }
         }
+        GoalType::Records => {
+            let fields = match cli
+                .selectors
+                .iter()
+                .map(|s| s.parse::<FieldSpec>())
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(fields) => fields,
+                Err(e) => {
+                    eprintln!("{} {}", "Error:".red().bold(), e);
+                    std::process::exit(1);
+                }
+            };
+            ExtractionGoal::ExtractRecords {
+                name: "record".to_string(),
+                container: cli.container,
+                fields,
+            }
+        }
+        GoalType::Article => ExtractionGoal::ExtractArticle,
+        GoalType::Metadata => ExtractionGoal::ExtractMetadata {
+            types: cli.schema_types,
+        },
     };
 
     let link_patterns: Vec<Regex> = cli
@@ -145,138 +583,274 @@ async fn main() {
         .filter_map(|p| Regex::new(p).ok())
         .collect();
 
+    let exclude_patterns: Vec<Regex> = cli
+        .exclude
+        .iter()
+        .filter_map(|p| Regex::new(p).ok())
+        .collect();
+
+    let forms = match cli.form {
+        Some(selector) => vec![FormSubmission {
+            selector,
+            page_pattern: None,
+            values: cli
+                .form_values
+                .iter()
+                .filter_map(|v| v.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }],
+        None => vec![],
+    };
+
+    let pagination = (cli.paginate || cli.next_selector.is_some() || cli.page_template.is_some())
+        .then(|| Pagination {
+            next_selector: cli.next_selector,
+            url_template: cli.page_template,
+            max_pages: cli.max_pages,
+            page_pattern: cli.page_pattern.map(Pattern),
+            ..Default::default()
+        });
+
     let task = Task {
         name: format!("Crawl {}", seed_url.host_str().unwrap_or("unknown")),
         seed_urls: vec![seed_url],
         goal,
         link_follow_patterns: link_patterns,
+        link_exclude_patterns: exclude_patterns,
+        discover_sitemaps: cli.sitemaps,
+        forms,
+        pagination,
     };
 
-    let report = run_agent(task, config, safety).await;
+    let format = if cli.json { Some(OutputFormat::Json) } else { cli.output.map(Into::into) };
+    let writer = format.map(|format| open_output(format, cli.output_file.as_deref(), false));
+    let graph = cli.graph.map(|path| (graph_format(&path), path));
+    let report = run_agent(task, config, cli.state, writer.clone()).await;
+    if let Some((render, path)) = graph {
+        if let Err(e) = std::fs::write(&path, render(&report)) {
+            output_failed(format!("cannot write {}: {}", path.display(), e));
+        }
+    }
 
-    if cli.json {
-        println!("{}", report.to_json());
-    } else {
+    // Results written to stdout replace the summary.
+    if writer.is_none() || cli.output_file.is_some() {
         report.print_summary();
     }
+    if let Some(writer) = writer {
+        close_output(writer);
+    }
 }
 
-async fn run_agent(task: Task, config: AgentConfig, safety: SafetyConfig) -> Report {
-    let task_name = task.name.clone();
-    let start = Instant::now();
-
-    let browser = match Browser::new(&config) {
-        Ok(b) => b,
-        Err(e) => {
-            eprintln!("{} Failed to create browser: {}", "Error:".red().bold(), e);
-            return Report {
-                task_name,
-                items: vec![],
-                pages_visited: 0,
-                pages_skipped: 0,
-                safety_blocks: vec![format!("Browser init failed: {}", e)],
-                duration: start.elapsed(),
-            };
-        }
-    };
-
-    let mut guardrails = Guardrails::new(safety);
-    let mut planner = Planner::new(task);
-
-    loop {
-        let action = planner.next_action();
-
-        match action {
-            AgentAction::Visit(url, depth) => {
-                // Check all guardrails before proceeding
-                if let Err(e) = guardrails.check_budget() {
-                    println!("  {} {}", "⛔".red(), e);
-                    break;
-                }
-
-                if let Err(e) = guardrails.check_depth(depth) {
-                    println!("  {} {}", "⏭️ ".yellow(), e);
-                    continue;
-                }
+/// How to render the link graph for `path`, chosen by its extension.
+fn graph_format(path: &Path) -> fn(&Report) -> String {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("dot" | "gv") => Report::to_dot,
+        Some("graphml") => Report::to_graphml,
+        _ => output_failed(format!(
+            "{}: graph files must end in .dot or .graphml",
+            path.display()
+        )),
+    }
+}
 
-                if let Err(e) = guardrails.check_url(&url) {
-                    println!("  {} {}", "🚫".yellow(), e);
-                    continue;
-                }
+/// An output writer shared between the agent's event callback and the
+/// code that opens and closes it.
+type SharedWriter = Arc<Mutex<Box<dyn OutputWriter>>>;
 
-                // Rate limit
-                guardrails.enforce_rate_limit().await;
+fn open_output(format: OutputFormat, path: Option<&Path>, many: bool) -> SharedWriter {
+    match output::create(format, path, many) {
+        Ok(writer) => Arc::new(Mutex::new(writer)),
+        Err(e) => output_failed(e),
+    }
+}
 
-                println!(
-                    "  {} [depth={}] {}",
-                    "→".blue(),
-                    depth,
-                    truncate_url(&url, 80)
-                );
+fn close_output(writer: SharedWriter) {
+    // Agents drop their callbacks, and with them their clones, when they
+    // finish, so this is the last reference.
+    let Some(writer) = Arc::into_inner(writer).and_then(|w| w.into_inner().ok()) else {
+        return;
+    };
+    if let Err(e) = writer.finish() {
+        output_failed(e);
+    }
+}
 
-                // Fetch page
-                let page = match browser.fetch(&url).await {
-                    Ok(p) => p,
-                    Err(e) => {
-                        println!("    {} Fetch failed: {}", "✗".red(), e);
-                        guardrails.record_request();
-                        continue;
-                    }
-                };
+/// Results that cannot be written make the crawl pointless; stop.
+fn output_failed(error: impl std::fmt::Display) -> ! {
+    eprintln!("{} {}", "Error:".red().bold(), error);
+    std::process::exit(1);
+}
 
-                guardrails.record_request();
+/// Run a task with an `Agent`, printing progress to the terminal, saving
+/// crawl state to `state` and writing results to `output` if given.
+async fn run_agent(
+    task: Task,
+    config: AgentConfig,
+    state: Option<PathBuf>,
+    output: Option<SharedWriter>,
+) -> Report {
+    let task_name = task.name.clone();
+    let mut agent = match Agent::new(task, config) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{} Failed to create agent: {}", "Error:".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
 
-                // Check content safety
-                if let Err(e) = guardrails.check_content(&page.body) {
-                    println!("    {} {}", "🚫".red(), e);
-                    continue;
-                }
+    if let Some(path) = state {
+        agent = match agent.with_state_file(path) {
+            Ok(a) => a,
+            Err(e) => {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        };
+    }
 
-                if page.status != 200 {
-                    println!("    {} HTTP {}", "⚠".yellow(), page.status);
-                    continue;
-                }
+    let Some(output) = output else {
+        return agent.on_event(print_event).run().await;
+    };
 
-                // Parse and process
-                let parsed = Browser::parse(&page);
-                let link_count = parsed.links.len();
-                let title = parsed
-                    .title
-                    .as_deref()
-                    .unwrap_or("(no title)")
-                    .to_string();
-
-                planner.process_page(&parsed, depth);
-
-                let new_items = planner.results.len();
-                println!(
-                    "    {} \"{}\" | {} links | {} items total",
-                    "✓".green(),
-                    truncate_str(&title, 50),
-                    link_count,
-                    new_items
-                );
-            }
-            AgentAction::Done => {
-                println!("\n  {} Agent finished - frontier exhausted", "✓".green().bold());
-                break;
+    if let Err(e) = output.lock().unwrap().start_task(&task_name) {
+        output_failed(e);
+    }
+    let events = Arc::clone(&output);
+    let report = agent
+        .on_event(move |event| {
+            print_event(event);
+            if let Err(e) = events.lock().unwrap().write_event(event) {
+                output_failed(e);
             }
-        }
+        })
+        .run()
+        .await;
+    if let Err(e) = output.lock().unwrap().end_task(&report) {
+        output_failed(e);
     }
+    report
+}
 
-    Report {
-        task_name,
-        items: planner.results,
-        pages_visited: planner.pages_visited,
-        pages_skipped: planner.pages_skipped,
-        safety_blocks: guardrails.safety_blocks,
-        duration: start.elapsed(),
+/// Print progress to stderr, so results written to stdout can be piped.
+fn print_event(event: &AgentEvent) {
+    match event {
+        AgentEvent::Visiting { url, depth } => {
+            eprintln!(
+                "  {} [depth={}] {}",
+                "→".blue(),
+                depth,
+                truncate_url(url, 80)
+            );
+        }
+        AgentEvent::VisitingNextPage { url, depth, page } => {
+            eprintln!(
+                "  {} [depth={} page={}] {}",
+                "→".blue(),
+                depth,
+                page,
+                truncate_url(url, 80)
+            );
+        }
+        AgentEvent::SubmittingForm { url, method, depth } => {
+            eprintln!(
+                "  {} [depth={}] {} {}",
+                "📝".blue(),
+                depth,
+                method,
+                truncate_url(url, 80)
+            );
+        }
+        AgentEvent::PageProcessed {
+            title,
+            links,
+            items_total,
+            ..
+        } => {
+            eprintln!(
+                "    {} \"{}\" | {} links | {} items total",
+                "✓".green(),
+                truncate_str(title.as_deref().unwrap_or("(no title)"), 50),
+                links,
+                items_total
+            );
+        }
+        AgentEvent::HttpStatus { status, .. } => {
+            eprintln!("    {} HTTP {}", "⚠".yellow(), status);
+        }
+        AgentEvent::FetchFailed { error, .. } => {
+            eprintln!("    {} Fetch failed: {}", "✗".red(), error);
+        }
+        AgentEvent::ContentSkipped { reason, .. } => {
+            eprintln!("    {} Skipped: {}", "⏭️ ".yellow(), reason);
+        }
+        AgentEvent::Retrying {
+            attempt,
+            delay,
+            reason,
+            ..
+        } => {
+            eprintln!(
+                "    {} {} (attempt {}), retrying in {:.1}s",
+                "↻".yellow(),
+                reason,
+                attempt,
+                delay.as_secs_f64()
+            );
+        }
+        AgentEvent::DepthExceeded { reason, .. } | AgentEvent::OverBudget { reason, .. } => {
+            eprintln!("  {} {}", "⏭️ ".yellow(), reason);
+        }
+        AgentEvent::UrlBlocked { reason, .. } => {
+            eprintln!("  {} {}", "🚫".yellow(), reason);
+        }
+        AgentEvent::ContentBlocked { reason, .. } => {
+            eprintln!("    {} {}", "🚫".red(), reason);
+        }
+        AgentEvent::BudgetExhausted { reason } => {
+            eprintln!("  {} {}", "⛔".red(), reason);
+        }
+        AgentEvent::SitemapProcessed { url, urls_added } => {
+            eprintln!(
+                "  {} Sitemap {} | {} URLs queued",
+                "🗺️ ".blue(),
+                truncate_url(url, 80),
+                urls_added
+            );
+        }
+        AgentEvent::NearDuplicate { duplicate_of, .. } => {
+            eprintln!(
+                "    {} Near-duplicate of {}, skipped",
+                "⏭️ ".yellow(),
+                truncate_str(duplicate_of, 80)
+            );
+        }
+        AgentEvent::Resumed {
+            pages_visited,
+            frontier,
+        } => {
+            eprintln!(
+                "  {} Resuming: {} pages already visited, {} URLs in frontier",
+                "↻".blue(),
+                pages_visited,
+                frontier
+            );
+        }
+        AgentEvent::CheckpointFailed { error } => {
+            eprintln!("  {} {}", "⚠".yellow(), error);
+        }
+        // Records and policy decisions repeat the events above; they end
+        // up in the report.
+        AgentEvent::PageRecorded { .. } | AgentEvent::PolicyDecided { .. } => {}
+        AgentEvent::FrontierExhausted => {
+            eprintln!("\n  {} Agent finished - frontier exhausted", "✓".green().bold());
+        }
     }
 }
 
 async fn run_demo() {
-    println!("\n{}", "Running demo: Extract headings from example.com".yellow());
-    println!(
+    eprintln!("\n{}", "Running demo: Extract headings from example.com".yellow());
+    eprintln!(
         "{}",
         "Safety: domain-locked to example.com, max 5 requests, depth 1\n".dimmed()
     );
@@ -285,12 +859,21 @@ async fn run_demo() {
         allowed_domains: vec!["example.com".to_string()],
         max_depth: 1,
         max_requests: 5,
+        max_duration_secs: None,
+        max_total_bytes: None,
+        max_requests_per_domain: None,
+        max_pages_per_path: HashMap::new(),
         rate_limit_ms: 500,
+        max_crawl_delay_ms: 30_000,
+        max_connections_per_host: 1,
         blocked_content_patterns: vec![],
+        robots_exempt_domains: vec![],
+        allowed_form_actions: vec![],
+        policy: PolicyConfig::default(),
     };
 
     let config = AgentConfig {
-        safety: safety.clone(),
+        safety,
         ..Default::default()
     };
 
@@ -301,9 +884,13 @@ async fn run_demo() {
             selectors: vec!["h1".to_string(), "h2".to_string(), "p".to_string()],
         },
         link_follow_patterns: vec![],
+        link_exclude_patterns: vec![],
+        discover_sitemaps: false,
+        forms: vec![],
+        pagination: None,
     };
 
-    let report = run_agent(task, config, safety).await;
+    let report = run_agent(task, config, None, None).await;
     report.print_summary();
 }
 
@@ -312,10 +899,81 @@ fn truncate_url(url: &Url, max_len: usize) -> String {
     truncate_str(s, max_len)
 }
 
+/// The first `max_len` characters of `s`, followed by `...` if it was cut.
 fn truncate_str(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len])
+    match s.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}...", &s[..end]),
+        None => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn crawl_args(args: &[&str]) -> CrawlArgs {
+        let cli = Cli::try_parse_from(["autonomous-web-agent"].iter().chain(args)).unwrap();
+        assert!(cli.command.is_none());
+        cli.crawl
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_crawl_defaults() {
+        let args = crawl_args(&["-u", "https://example.com/"]);
+        assert!(matches!(args.strategy, StrategyArg::Bfs));
+        assert!(matches!(args.redaction, RedactionArg::Mask));
+        assert_eq!(args.max_crawl_delay, 30_000);
+        assert_eq!(args.skip_near_duplicates, None);
+        assert_eq!(args.max_pages, 10);
+
+        let args = crawl_args(&["-u", "https://example.com/", "-d", "a.com,b.com", "-D", "1"]);
+        assert_eq!(args.domains, ["a.com", "b.com"]);
+        assert_eq!(args.max_depth, 1);
+    }
+
+    #[test]
+    fn test_conflicting_and_dependent_flags() {
+        let parse =
+            |args: &[&str]| Cli::try_parse_from(["autonomous-web-agent"].iter().chain(args));
+        assert!(parse(&["-u", "https://example.com/", "--page-template", "?p={page}"]).is_err());
+        assert!(parse(&["-u", "https://example.com/", "--json", "--output", "csv"]).is_err());
+        assert!(parse(&["-u", "https://example.com/", "--offline"]).is_err());
+        assert!(parse(&["--output-file", "out.jsonl"]).is_err());
+
+        let run = [
+            "run",
+            "tasks.yaml",
+            "--output",
+            "sqlite",
+            "--output-file",
+            "out.db",
+        ];
+        let cli = parse(&run).unwrap();
+        let Some(Command::Run { output, .. }) = cli.command else {
+            panic!("expected the run subcommand");
+        };
+        assert_eq!(output.map(OutputFormat::from), Some(OutputFormat::Sqlite));
+    }
+
+    #[test]
+    fn test_state_file_name() {
+        assert_eq!(state_file_name("Docs: Headings!"), "docs--headings.json");
+        assert_eq!(
+            state_file_name("crawl example.com"),
+            "crawl-example-com.json"
+        );
+    }
+
+    #[test]
+    fn test_truncate_str_keeps_whole_characters() {
+        assert_eq!(truncate_str("short", 10), "short");
+        assert_eq!(truncate_str("naïve café", 3), "naï...");
+        assert_eq!(truncate_str("日本語のタイトル", 3), "日本語...");
     }
 }
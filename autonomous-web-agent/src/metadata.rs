@@ -0,0 +1,371 @@
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// OpenGraph namespaces, including the type-specific ones such as
+/// `article:published_time` and `product:price:amount`.
+const OPENGRAPH_PREFIXES: &[&str] = &[
+    "og:", "article:", "product:", "book:", "profile:", "music:", "video:",
+];
+
+/// Where a piece of structured data came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataSource {
+    JsonLd,
+    OpenGraph,
+    Twitter,
+    Microdata,
+    Rdfa,
+}
+
+impl fmt::Display for MetadataSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetadataSource::JsonLd => write!(f, "json_ld"),
+            MetadataSource::OpenGraph => write!(f, "opengraph"),
+            MetadataSource::Twitter => write!(f, "twitter"),
+            MetadataSource::Microdata => write!(f, "microdata"),
+            MetadataSource::Rdfa => write!(f, "rdfa"),
+        }
+    }
+}
+
+/// One block of structured data: a schema.org item, or the page's
+/// OpenGraph or Twitter card tags as one object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub source: MetadataSource,
+    pub value: Value,
+}
+
+impl Metadata {
+    /// The item's schema.org types, without the vocabulary prefix. Social
+    /// meta tags have none.
+    pub fn types(&self) -> Vec<&str> {
+        match self.value.get("@type") {
+            Some(Value::String(t)) => vec![strip_vocabulary(t)],
+            Some(Value::Array(ts)) => ts
+                .iter()
+                .filter_map(Value::as_str)
+                .map(strip_vocabulary)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Whether the item has one of `types` (case-insensitive). An empty
+    /// list matches everything; untyped OpenGraph and Twitter blocks are
+    /// always kept.
+    pub fn matches_types(&self, types: &[String]) -> bool {
+        if types.is_empty()
+            || matches!(
+                self.source,
+                MetadataSource::OpenGraph | MetadataSource::Twitter
+            )
+        {
+            return true;
+        }
+        self.types()
+            .iter()
+            .any(|t| types.iter().any(|wanted| wanted.eq_ignore_ascii_case(t)))
+    }
+}
+
+/// Collect the structured data published by a page: schema.org JSON-LD
+/// blocks, OpenGraph and Twitter card meta tags, and microdata and RDFa
+/// items. Nested items are kept inside their parent rather than repeated.
+pub fn extract(document: &Html) -> Vec<Metadata> {
+    let mut found = json_ld(document);
+    for (source, value) in [
+        (MetadataSource::OpenGraph, opengraph(document)),
+        (MetadataSource::Twitter, twitter(document)),
+    ] {
+        if let Some(value) = value {
+            found.push(Metadata { source, value });
+        }
+    }
+    found.extend(items(document, &Vocabulary::MICRODATA));
+    found.extend(items(document, &Vocabulary::RDFA));
+    found
+}
+
+fn json_ld(document: &Html) -> Vec<Metadata> {
+    let selector = Selector::parse("script[type='application/ld+json']").expect("valid selector");
+    let mut found = Vec::new();
+    for script in document.select(&selector) {
+        let text: String = script.text().collect();
+        // Malformed blocks are common in the wild and simply skipped.
+        if let Ok(value) = serde_json::from_str::<Value>(text.trim()) {
+            flatten_json_ld(value, &mut found);
+        }
+    }
+    found
+}
+
+/// Split top-level arrays and `@graph` containers into their items.
+fn flatten_json_ld(value: Value, found: &mut Vec<Metadata>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                flatten_json_ld(value, found);
+            }
+        }
+        Value::Object(mut map) if map.contains_key("@graph") => {
+            if let Some(graph) = map.remove("@graph") {
+                flatten_json_ld(graph, found);
+            }
+        }
+        Value::Object(_) => found.push(Metadata {
+            source: MetadataSource::JsonLd,
+            value,
+        }),
+        _ => {}
+    }
+}
+
+fn opengraph(document: &Html) -> Option<Value> {
+    meta_tags(document, |name| {
+        OPENGRAPH_PREFIXES.iter().any(|p| name.starts_with(p))
+    })
+}
+
+fn twitter(document: &Html) -> Option<Value> {
+    meta_tags(document, |name| name.starts_with("twitter:"))
+}
+
+/// The `<meta>` tags whose `property` or `name` is accepted by `wanted`,
+/// as one object. Repeated tags such as `og:image` become arrays.
+fn meta_tags(document: &Html, wanted: impl Fn(&str) -> bool) -> Option<Value> {
+    let selector = Selector::parse("meta[content]").expect("valid selector");
+    let mut map = Map::new();
+    for meta in document.select(&selector) {
+        let Some(name) = meta
+            .value()
+            .attr("property")
+            .or_else(|| meta.value().attr("name"))
+        else {
+            continue;
+        };
+        let name = name.trim().to_ascii_lowercase();
+        if wanted(&name) {
+            let content = meta.value().attr("content").unwrap_or("").trim();
+            insert(&mut map, &name, Value::String(content.to_string()));
+        }
+    }
+    (!map.is_empty()).then_some(Value::Object(map))
+}
+
+/// The attributes that mark up items and their properties, which differ
+/// between microdata and RDFa but are walked the same way.
+struct Vocabulary {
+    source: MetadataSource,
+    scope: &'static str,
+    property: &'static str,
+    id: &'static [&'static str],
+}
+
+impl Vocabulary {
+    const MICRODATA: Vocabulary = Vocabulary {
+        source: MetadataSource::Microdata,
+        scope: "itemscope",
+        property: "itemprop",
+        id: &["itemid"],
+    };
+    const RDFA: Vocabulary = Vocabulary {
+        source: MetadataSource::Rdfa,
+        scope: "typeof",
+        property: "property",
+        id: &["resource", "about"],
+    };
+}
+
+/// The top-level items, i.e. those that are not a property of another.
+fn items(document: &Html, vocabulary: &Vocabulary) -> Vec<Metadata> {
+    let selector = Selector::parse(&format!(
+        "[{}]:not([{}])",
+        vocabulary.scope, vocabulary.property
+    ))
+    .expect("valid selector");
+    document
+        .select(&selector)
+        .map(|el| Metadata {
+            source: vocabulary.source,
+            value: item(el, vocabulary),
+        })
+        .collect()
+}
+
+fn item(el: ElementRef, vocabulary: &Vocabulary) -> Value {
+    let mut map = Map::new();
+    // Microdata names the type in `itemtype`; RDFa in `typeof` itself.
+    let types: Vec<Value> = el
+        .value()
+        .attr("itemtype")
+        .or_else(|| el.value().attr("typeof"))
+        .unwrap_or("")
+        .split_whitespace()
+        .map(|t| Value::String(strip_vocabulary(t).to_string()))
+        .collect();
+    match types.len() {
+        0 => {}
+        1 => {
+            map.insert("@type".to_string(), types[0].clone());
+        }
+        _ => {
+            map.insert("@type".to_string(), Value::Array(types));
+        }
+    }
+    if let Some(id) = vocabulary.id.iter().find_map(|a| el.value().attr(a)) {
+        map.insert("@id".to_string(), Value::String(id.to_string()));
+    }
+    properties(el, vocabulary, &mut map);
+    Value::Object(map)
+}
+
+/// Collect the properties below `el`, stopping at nested items, whose
+/// properties are their own.
+fn properties(el: ElementRef, vocabulary: &Vocabulary, map: &mut Map<String, Value>) {
+    for child in el.children().filter_map(ElementRef::wrap) {
+        let scoped = child.value().attr(vocabulary.scope).is_some();
+        if let Some(names) = child.value().attr(vocabulary.property) {
+            let value = if scoped {
+                item(child, vocabulary)
+            } else {
+                property_value(child)
+            };
+            for name in names.split_whitespace() {
+                insert(map, strip_vocabulary(name), value.clone());
+            }
+        }
+        if !scoped {
+            properties(child, vocabulary, map);
+        }
+    }
+}
+
+/// A property's value: an explicit `content`, the element's URL or
+/// machine-readable value, or its text.
+fn property_value(el: ElementRef) -> Value {
+    let attr = |name| el.value().attr(name);
+    let value = attr("content").or_else(|| match el.value().name() {
+        "a" | "area" | "link" => attr("href"),
+        "img" | "audio" | "video" | "source" | "embed" | "iframe" | "track" => attr("src"),
+        "object" => attr("data"),
+        "time" => attr("datetime"),
+        "data" | "meter" => attr("value"),
+        _ => attr("resource"),
+    });
+    let value = match value {
+        Some(value) => value.trim().to_string(),
+        None => el.text().collect::<Vec<_>>().join(" "),
+    };
+    Value::String(value.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Add a value under `key`, turning repeated keys into arrays.
+fn insert(map: &mut Map<String, Value>, key: &str, value: Value) {
+    match map.get_mut(key) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+        None => {
+            map.insert(key.to_string(), value);
+        }
+    }
+}
+
+fn strip_vocabulary(name: &str) -> &str {
+    ["https://schema.org/", "http://schema.org/", "schema:"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PAGE: &str = r#"<html><head>
+        <meta property="og:title" content="Trail Shoe">
+        <meta property="og:image" content="https://example.com/1.jpg">
+        <meta property="og:image" content="https://example.com/2.jpg">
+        <meta property="product:price:amount" content="89.00">
+        <meta name="twitter:card" content="summary">
+        <meta name="description" content="Not social metadata">
+        <script type="application/ld+json">
+          {"@context": "https://schema.org", "@graph": [
+            {"@type": "Product", "name": "Trail Shoe", "offers": {"@type": "Offer", "price": "89.00"}},
+            {"@type": "BreadcrumbList", "itemListElement": []}
+          ]}
+        </script>
+        <script type="application/ld+json">{ not json </script>
+      </head><body>
+        <div itemscope itemtype="https://schema.org/Review">
+          <span itemprop="author" itemscope itemtype="https://schema.org/Person">
+            <span itemprop="name">Ann</span>
+          </span>
+          <div><meta itemprop="ratingValue" content="4"><p itemprop="reviewBody">Comfortable,  light.</p></div>
+          <a itemprop="url" href="https://example.com/r/1">Permalink</a>
+        </div>
+        <div vocab="https://schema.org/" typeof="Event">
+          <span property="name">Launch</span>
+          <time property="startDate" datetime="2024-05-01">May 1</time>
+        </div>
+      </body></html>"#;
+
+    #[test]
+    fn test_extracts_each_source() {
+        let found = extract(&Html::parse_document(PAGE));
+        let sources: Vec<String> = found.iter().map(|m| m.source.to_string()).collect();
+        assert_eq!(
+            sources,
+            [
+                "json_ld",
+                "json_ld",
+                "opengraph",
+                "twitter",
+                "microdata",
+                "rdfa"
+            ]
+        );
+
+        assert_eq!(found[0].value["offers"]["price"], "89.00");
+        assert_eq!(
+            found[2].value,
+            json!({
+                "og:title": "Trail Shoe",
+                "og:image": ["https://example.com/1.jpg", "https://example.com/2.jpg"],
+                "product:price:amount": "89.00",
+            })
+        );
+        assert_eq!(found[3].value, json!({"twitter:card": "summary"}));
+        assert_eq!(
+            found[4].value,
+            json!({
+                "@type": "Review",
+                "author": {"@type": "Person", "name": "Ann"},
+                "ratingValue": "4",
+                "reviewBody": "Comfortable, light.",
+                "url": "https://example.com/r/1",
+            })
+        );
+        assert_eq!(
+            found[5].value,
+            json!({"@type": "Event", "name": "Launch", "startDate": "2024-05-01"})
+        );
+    }
+
+    #[test]
+    fn test_matches_types() {
+        let found = extract(&Html::parse_document(PAGE));
+        let wanted = vec!["product".to_string(), "Event".to_string()];
+        let kept: Vec<String> = found
+            .iter()
+            .filter(|m| m.matches_types(&wanted))
+            .map(|m| m.source.to_string())
+            .collect();
+        assert_eq!(kept, ["json_ld", "opengraph", "twitter", "rdfa"]);
+        assert_eq!(found[4].types(), ["Review"]);
+        assert!(found.iter().all(|m| m.matches_types(&[])));
+    }
+}
@@ -0,0 +1,561 @@
+use crate::agent::AgentEvent;
+use crate::error::{AgentError, Result};
+use crate::extractor::ExtractedItem;
+use crate::report::Report;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// A format to write crawl results in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The full report as one pretty-printed JSON document, written when
+    /// the crawl ends.
+    Json,
+    /// One JSON object per extracted item, written as pages are processed.
+    Jsonl,
+    /// One row per extracted item, written as pages are processed.
+    Csv,
+    /// A SQLite database with `pages`, `items`, `links` and
+    /// `safety_events` tables.
+    Sqlite,
+}
+
+/// Receives a crawl's results as it runs.
+///
+/// Writers are fed the agent's events, so streaming formats can write
+/// items as soon as they are extracted. Items restored from a saved crawl
+/// state were written by the run that extracted them and are not repeated.
+pub trait OutputWriter: Send {
+    /// A task is starting; everything written until `end_task` belongs to it.
+    fn start_task(&mut self, task_name: &str) -> Result<()>;
+    fn write_event(&mut self, event: &AgentEvent) -> Result<()>;
+    fn end_task(&mut self, report: &Report) -> Result<()>;
+    /// Write anything still buffered and close the output.
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// Create a writer for `format`, writing to `path` or, for every format
+/// but SQLite, to stdout. `many` selects a JSON array of reports over a
+/// single report object.
+pub fn create(
+    format: OutputFormat,
+    path: Option<&Path>,
+    many: bool,
+) -> Result<Box<dyn OutputWriter>> {
+    if format == OutputFormat::Sqlite {
+        let path =
+            path.ok_or_else(|| AgentError::Output("SQLite output needs a file".to_string()))?;
+        return Ok(Box::new(SqliteWriter::open(path)?));
+    }
+
+    let out: Box<dyn Write + Send> = match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|e| {
+            AgentError::Output(format!("cannot create {}: {}", path.display(), e))
+        })?)),
+        None => Box::new(io::stdout()),
+    };
+    Ok(match format {
+        OutputFormat::Json => Box::new(JsonWriter::new(out, many)),
+        OutputFormat::Jsonl => Box::new(JsonLinesWriter::new(out)),
+        OutputFormat::Csv => Box::new(CsvWriter::new(out)),
+        OutputFormat::Sqlite => unreachable!(),
+    })
+}
+
+fn io_error(e: impl std::fmt::Display) -> AgentError {
+    AgentError::Output(e.to_string())
+}
+
+/// Collects reports and writes them as pretty-printed JSON at the end.
+pub struct JsonWriter<W> {
+    out: W,
+    many: bool,
+    reports: Vec<serde_json::Value>,
+}
+
+impl<W: Write + Send> JsonWriter<W> {
+    pub fn new(out: W, many: bool) -> Self {
+        Self {
+            out,
+            many,
+            reports: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write + Send> OutputWriter for JsonWriter<W> {
+    fn start_task(&mut self, _task_name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_event(&mut self, _event: &AgentEvent) -> Result<()> {
+        Ok(())
+    }
+
+    fn end_task(&mut self, report: &Report) -> Result<()> {
+        self.reports
+            .push(serde_json::to_value(report).map_err(io_error)?);
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        let json = match (self.many, self.reports.len()) {
+            (false, 1) => serde_json::to_string_pretty(&self.reports[0]),
+            _ => serde_json::to_string_pretty(&self.reports),
+        }
+        .map_err(io_error)?;
+        writeln!(self.out, "{}", json).map_err(io_error)?;
+        self.out.flush().map_err(io_error)
+    }
+}
+
+/// An extracted item tagged with its task, as written to JSON Lines.
+#[derive(Serialize)]
+struct ItemRow<'a> {
+    task: &'a str,
+    #[serde(flatten)]
+    item: &'a ExtractedItem,
+}
+
+/// Writes one JSON object per item, flushing after every page.
+pub struct JsonLinesWriter<W> {
+    out: W,
+    task: String,
+}
+
+impl<W: Write + Send> JsonLinesWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            task: String::new(),
+        }
+    }
+}
+
+impl<W: Write + Send> OutputWriter for JsonLinesWriter<W> {
+    fn start_task(&mut self, task_name: &str) -> Result<()> {
+        self.task = task_name.to_string();
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &AgentEvent) -> Result<()> {
+        let AgentEvent::PageProcessed { items, .. } = event else {
+            return Ok(());
+        };
+        for item in items {
+            let row = ItemRow {
+                task: &self.task,
+                item,
+            };
+            serde_json::to_writer(&mut self.out, &row).map_err(io_error)?;
+            self.out.write_all(b"\n").map_err(io_error)?;
+        }
+        self.out.flush().map_err(io_error)
+    }
+
+    fn end_task(&mut self, _report: &Report) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.out.flush().map_err(io_error)
+    }
+}
+
+/// Writes `task,source_url,field,value` rows under a header row,
+/// flushing after every page. Non-string values are written as JSON.
+pub struct CsvWriter<W: Write> {
+    out: csv::Writer<W>,
+    task: String,
+    header_written: bool,
+}
+
+impl<W: Write + Send> CsvWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out: csv::Writer::from_writer(out),
+            task: String::new(),
+            header_written: false,
+        }
+    }
+}
+
+impl<W: Write + Send> OutputWriter for CsvWriter<W> {
+    fn start_task(&mut self, task_name: &str) -> Result<()> {
+        self.task = task_name.to_string();
+        if !self.header_written {
+            self.out
+                .write_record(["task", "source_url", "field", "value"])
+                .map_err(io_error)?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &AgentEvent) -> Result<()> {
+        let AgentEvent::PageProcessed { items, .. } = event else {
+            return Ok(());
+        };
+        for item in items {
+            let value = item.value_text();
+            self.out
+                .write_record([self.task.as_str(), &item.source_url, &item.field, &value])
+                .map_err(io_error)?;
+        }
+        self.out.flush().map_err(io_error)
+    }
+
+    fn end_task(&mut self, _report: &Report) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.out.flush().map_err(io_error)
+    }
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS pages (
+        id INTEGER PRIMARY KEY,
+        task TEXT NOT NULL,
+        url TEXT NOT NULL,
+        referrer TEXT,
+        depth INTEGER NOT NULL,
+        decision TEXT NOT NULL,
+        reason TEXT,
+        status INTEGER,
+        fetch_ms INTEGER,
+        bytes INTEGER,
+        title TEXT,
+        items INTEGER NOT NULL,
+        attempts INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS items (
+        id INTEGER PRIMARY KEY,
+        task TEXT NOT NULL,
+        source_url TEXT NOT NULL,
+        field TEXT NOT NULL,
+        value TEXT NOT NULL,
+        value_json TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS links (
+        id INTEGER PRIMARY KEY,
+        task TEXT NOT NULL,
+        run TEXT NOT NULL,
+        from_url TEXT NOT NULL,
+        to_url TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS safety_events (
+        id INTEGER PRIMARY KEY,
+        task TEXT NOT NULL,
+        run TEXT NOT NULL,
+        seq INTEGER NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS items_task_field ON items (task, field);
+    CREATE UNIQUE INDEX IF NOT EXISTS links_edge ON links (task, run, from_url, to_url);
+    CREATE UNIQUE INDEX IF NOT EXISTS safety_events_seq ON safety_events (task, run, seq);
+";
+
+/// The schema version, stored in the database's `user_version`. Databases
+/// written by a newer agent are refused rather than written to in a layout
+/// they do not expect.
+const SCHEMA_VERSION: i64 = 1;
+
+/// Writes the audit record of each page and the items extracted from it to
+/// a SQLite database as the crawl runs, and the task's links and safety
+/// events when it ends. Tables are created if missing,
+/// so several crawls can share a database.
+///
+/// A resumed crawl's report repeats the links and safety events of the
+/// run it continues. Both are keyed by the report's run id, which a resume
+/// keeps, so they are not written twice; a separate run of the same task
+/// gets a new id and all its rows.
+pub struct SqliteWriter {
+    conn: Connection,
+    task: String,
+}
+
+impl SqliteWriter {
+    pub fn open(path: &Path) -> Result<Self> {
+        let error =
+            |e: &dyn std::fmt::Display| AgentError::Output(format!("{}: {}", path.display(), e));
+        let conn = Connection::open(path).map_err(|e| error(&e))?;
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .map_err(|e| error(&e))?;
+        if version > SCHEMA_VERSION {
+            return Err(error(&format!(
+                "database schema version {} is newer than this agent's ({})",
+                version, SCHEMA_VERSION
+            )));
+        }
+        if version < SCHEMA_VERSION {
+            conn.execute_batch(SCHEMA)
+                .and_then(|_| conn.pragma_update(None, "user_version", SCHEMA_VERSION))
+                .map_err(|e| error(&e))?;
+        }
+        Ok(Self {
+            conn,
+            task: String::new(),
+        })
+    }
+}
+
+impl OutputWriter for SqliteWriter {
+    fn start_task(&mut self, task_name: &str) -> Result<()> {
+        self.task = task_name.to_string();
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &AgentEvent) -> Result<()> {
+        let items = match event {
+            AgentEvent::PageProcessed { items, .. } => items,
+            AgentEvent::PageRecorded { record } => {
+                self.conn
+                    .execute(
+                        "INSERT INTO pages (task, url, referrer, depth, decision, reason, status, fetch_ms, bytes, title, items, attempts) \
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                        params![
+                            self.task,
+                            record.url,
+                            record.referrer,
+                            record.depth as i64,
+                            record.decision.to_string(),
+                            record.reason,
+                            record.status,
+                            record.fetch_ms.map(|ms| ms as i64),
+                            record.bytes.map(|b| b as i64),
+                            record.title,
+                            record.items as i64,
+                            record.attempts
+                        ],
+                    )
+                    .map_err(io_error)?;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        let tx = self.conn.transaction().map_err(io_error)?;
+        for item in items {
+            tx.execute(
+                "INSERT INTO items (task, source_url, field, value, value_json) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    self.task,
+                    item.source_url,
+                    item.field,
+                    item.value_text(),
+                    item.value.to_string()
+                ],
+            )
+            .map_err(io_error)?;
+        }
+        tx.commit().map_err(io_error)
+    }
+
+    fn end_task(&mut self, report: &Report) -> Result<()> {
+        let tx = self.conn.transaction().map_err(io_error)?;
+        for link in &report.links {
+            tx.execute(
+                "INSERT OR IGNORE INTO links (task, run, from_url, to_url) VALUES (?1, ?2, ?3, ?4)",
+                params![self.task, report.run_id, link.from, link.to],
+            )
+            .map_err(io_error)?;
+        }
+        for (seq, message) in report.safety_blocks.iter().enumerate() {
+            tx.execute(
+                "INSERT OR IGNORE INTO safety_events (task, run, seq, message) VALUES (?1, ?2, ?3, ?4)",
+                params![self.task, report.run_id, seq as i64, message],
+            )
+            .map_err(io_error)?;
+        }
+        tx.commit().map_err(io_error)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.conn.close().map_err(|(_, e)| io_error(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::{Decision, Link, PageRecord};
+    use serde_json::json;
+    use std::time::Duration;
+    use url::Url;
+
+    fn item(field: &str, value: serde_json::Value) -> ExtractedItem {
+        ExtractedItem {
+            source_url: "https://example.com/".to_string(),
+            field: field.to_string(),
+            value,
+        }
+    }
+
+    fn page_event() -> AgentEvent {
+        AgentEvent::PageProcessed {
+            url: Url::parse("https://example.com/").unwrap(),
+            title: Some("Home".to_string()),
+            links: 3,
+            items: vec![
+                item("h1", json!("Hello, \"world\"")),
+                item("product", json!({"price": 25})),
+            ],
+            items_total: 2,
+        }
+    }
+
+    fn report() -> Report {
+        Report {
+            task_name: "docs".to_string(),
+            run_id: "run-1".to_string(),
+            items: vec![],
+            pages_visited: 1,
+            pages_skipped: 0,
+            budget_exhausted: None,
+            bytes_downloaded: 0,
+            safety_blocks: vec!["Blocked: https://example.com/private".to_string()],
+            policy_denials: vec![],
+            redactions: Default::default(),
+            errors: vec![],
+            pages: vec![],
+            links: vec![Link {
+                from: "https://example.com/".to_string(),
+                to: "https://example.com/a".to_string(),
+            }],
+            duration: Duration::ZERO,
+        }
+    }
+
+    fn record_event() -> AgentEvent {
+        AgentEvent::PageRecorded {
+            record: PageRecord {
+                url: "https://example.com/".to_string(),
+                referrer: None,
+                depth: 0,
+                decision: Decision::Processed,
+                reason: None,
+                status: Some(200),
+                fetch_ms: Some(15),
+                bytes: Some(512),
+                title: Some("Home".to_string()),
+                items: 2,
+                attempts: 1,
+            },
+        }
+    }
+
+    fn run(writer: &mut dyn OutputWriter) {
+        writer.start_task("docs").unwrap();
+        writer.write_event(&page_event()).unwrap();
+        writer.write_event(&record_event()).unwrap();
+        writer.end_task(&report()).unwrap();
+    }
+
+    #[test]
+    fn test_jsonl_and_csv() {
+        let mut jsonl = JsonLinesWriter::new(Vec::new());
+        run(&mut jsonl);
+        let lines: Vec<serde_json::Value> = String::from_utf8(jsonl.out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["task"], "docs");
+        assert_eq!(lines[1]["value"]["price"], 25);
+
+        let mut csv = CsvWriter::new(Vec::new());
+        run(&mut csv);
+        let csv = String::from_utf8(csv.out.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "task,source_url,field,value\n\
+             docs,https://example.com/,h1,\"Hello, \"\"world\"\"\"\n\
+             docs,https://example.com/,product,\"{\"\"price\"\":25}\"\n"
+        );
+    }
+
+    #[test]
+    fn test_sqlite_tables() {
+        let path = std::env::temp_dir().join(format!("agent-output-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut writer = Box::new(SqliteWriter::open(&path).unwrap());
+        run(writer.as_mut());
+        writer.finish().unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(count("pages"), 1);
+        assert_eq!(count("items"), 2);
+        assert_eq!(count("links"), 1);
+        assert_eq!(count("safety_events"), 1);
+
+        let decision: String = conn
+            .query_row("SELECT decision FROM pages", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(decision, "processed");
+
+        let price: i64 = conn
+            .query_row(
+                "SELECT json_extract(value_json, '$.price') FROM items WHERE field = 'product'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(price, 25);
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_resumed_and_separate_runs() {
+        let path = std::env::temp_dir().join(format!("agent-runs-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // A resumed crawl reports the earlier run's links and events again.
+        let mut writer = Box::new(SqliteWriter::open(&path).unwrap());
+        run(writer.as_mut());
+        writer.start_task("docs").unwrap();
+        let mut resumed = report();
+        resumed.safety_blocks.push(resumed.safety_blocks[0].clone());
+        writer.end_task(&resumed).unwrap();
+        writer.finish().unwrap();
+
+        let count = |table: &str| -> i64 {
+            let conn = Connection::open(&path).unwrap();
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(count("links"), 1);
+        // The same message logged again later is a new event.
+        assert_eq!(count("safety_events"), 2);
+
+        // A separate run of the task into the same database keeps its rows.
+        let mut writer = Box::new(SqliteWriter::open(&path).unwrap());
+        writer.start_task("docs").unwrap();
+        let mut separate = report();
+        separate.run_id = "run-2".to_string();
+        writer.end_task(&separate).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(count("links"), 2);
+        assert_eq!(count("safety_events"), 3);
+
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        drop(conn);
+        let error = SqliteWriter::open(&path).err().unwrap().to_string();
+        assert!(error.contains("newer"), "{}", error);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
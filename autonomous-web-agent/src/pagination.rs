@@ -0,0 +1,99 @@
+use crate::browser::ParsedPage;
+use crate::task::Pagination;
+use scraper::Selector;
+use url::Url;
+
+/// The URL of the page after `page`, which is page number `current` of
+/// its series, or `None` if the series ends here.
+pub fn next_page(page: &ParsedPage, pagination: &Pagination, current: usize) -> Option<Url> {
+    if current >= pagination.max_pages {
+        return None;
+    }
+
+    if let Some(template) = &pagination.url_template {
+        if !template.contains("{page}") {
+            return None;
+        }
+        let number = pagination.start_page + current;
+        return page
+            .url
+            .join(&template.replace("{page}", &number.to_string()))
+            .ok();
+    }
+
+    let next = match &pagination.next_selector {
+        Some(selector) => first_href(page, selector),
+        None => first_href(page, r#"link[rel~="next"], a[rel~="next"]"#),
+    }?;
+
+    // A "next" link back to the same page would loop forever.
+    if next == page.url {
+        return None;
+    }
+    Some(next)
+}
+
+fn first_href(page: &ParsedPage, selector: &str) -> Option<Url> {
+    let selector = Selector::parse(selector).ok()?;
+    page.document
+        .select(&selector)
+        .filter_map(|el| el.value().attr("href"))
+        .filter_map(|href| page.url.join(href).ok())
+        .find(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::{Browser, PageContent};
+    use crate::config::ContentKind;
+
+    fn page(url: &str, html: &str) -> ParsedPage {
+        Browser::parse(&PageContent {
+            url: Url::parse(url).unwrap(),
+            status: 200,
+            kind: ContentKind::Html,
+            body: html.to_string(),
+            retry_after: None,
+        })
+    }
+
+    #[test]
+    fn test_rel_next_and_selector() {
+        let listing = page(
+            "https://example.com/list?page=1",
+            r#"<head><link rel="next" href="?page=2"></head>
+               <body><a class="more" href="/list/p2">More</a></body>"#,
+        );
+
+        let by_rel = next_page(&listing, &Pagination::default(), 1).unwrap();
+        assert_eq!(by_rel.as_str(), "https://example.com/list?page=2");
+
+        let by_selector = Pagination {
+            next_selector: Some("a.more".to_string()),
+            ..Default::default()
+        };
+        let next = next_page(&listing, &by_selector, 1).unwrap();
+        assert_eq!(next.as_str(), "https://example.com/list/p2");
+
+        let no_next = page("https://example.com/list?page=9", "<p>Last page</p>");
+        assert_eq!(next_page(&no_next, &Pagination::default(), 9), None);
+    }
+
+    #[test]
+    fn test_url_template_and_max_pages() {
+        let pagination = Pagination {
+            url_template: Some("/search?q=rust&p={page}".to_string()),
+            start_page: 0,
+            max_pages: 3,
+            ..Default::default()
+        };
+        let first = page("https://example.com/search?q=rust", "");
+
+        // Page 1 of the series is p=0, so page 2 is p=1.
+        let next = next_page(&first, &pagination, 1).unwrap();
+        assert_eq!(next.as_str(), "https://example.com/search?q=rust&p=1");
+        assert!(next_page(&first, &pagination, 2).is_some());
+        assert_eq!(next_page(&first, &pagination, 3), None);
+    }
+}
@@ -1,70 +1,496 @@
 use crate::browser::ParsedPage;
+use crate::config::{DedupConfig, FrontierConfig};
+use crate::dedup::{self, ContentIndex};
 use crate::extractor::{self, ExtractedItem};
+use crate::forms::{self, FormRequest};
+use crate::pagination;
+use crate::frontier::{Frontier, LinkCandidate, QueuedVisit, Scorer, WeightedScorer};
+use crate::state::{CrawlState, PendingVisit};
 use crate::task::{AgentAction, Task};
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use url::Url;
 
 pub struct Planner {
     task: Task,
+    dedup: DedupConfig,
+    /// Canonical keys (see `dedup::canonical_key`) of URLs already visited.
     visited: HashSet<String>,
-    frontier: VecDeque<(Url, usize)>,
+    content: ContentIndex,
+    frontier: Frontier,
+    scorer: Box<dyn Scorer>,
+    /// Filled-in forms waiting to be submitted, ahead of the frontier.
+    submissions: VecDeque<(FormRequest, usize)>,
+    /// Keys of form submissions already queued.
+    submitted: HashSet<String>,
+    /// Next pages of paginated series as `(url, depth, page number)`,
+    /// visited ahead of the frontier.
+    next_pages: VecDeque<(Url, usize, usize)>,
+    /// Page numbers of queued next pages, by canonical key.
+    page_numbers: HashMap<String, usize>,
+    /// The page each queued URL was first found on, by canonical key.
+    referrers: HashMap<String, Url>,
     pub results: Vec<ExtractedItem>,
     pub pages_visited: usize,
     pub pages_skipped: usize,
 }
 
 impl Planner {
-    pub fn new(task: Task) -> Self {
-        let mut frontier = VecDeque::new();
-        for url in &task.seed_urls {
-            frontier.push_back((url.clone(), 0));
-        }
+    pub fn new(task: Task, dedup: DedupConfig, frontier: FrontierConfig) -> Self {
+        let scorer = WeightedScorer::new(frontier.clone(), task.link_follow_patterns.clone());
+        let seeds = task.seed_urls.clone();
 
-        Self {
+        let mut planner = Self {
             task,
+            dedup,
             visited: HashSet::new(),
-            frontier,
+            content: ContentIndex::default(),
+            frontier: Frontier::new(frontier.strategy),
+            scorer: Box::new(scorer),
+            submissions: VecDeque::new(),
+            submitted: HashSet::new(),
+            next_pages: VecDeque::new(),
+            page_numbers: HashMap::new(),
+            referrers: HashMap::new(),
             results: Vec::new(),
             pages_visited: 0,
             pages_skipped: 0,
+        };
+        for url in seeds {
+            planner.enqueue(url, 0, None, None);
+        }
+        planner
+    }
+
+    /// Rank best-first frontier candidates with `scorer` instead of the
+    /// default `WeightedScorer`. URLs already queued keep their scores.
+    pub fn set_scorer(&mut self, scorer: Box<dyn Scorer>) {
+        self.scorer = scorer;
+    }
+
+    fn enqueue(&mut self, url: Url, depth: usize, anchor_text: Option<&str>, source: Option<&Url>) {
+        if let Some(source) = source {
+            self.referrers
+                .entry(self.key(&url))
+                .or_insert_with(|| source.clone());
         }
+        let score = self.scorer.score(&LinkCandidate {
+            url: &url,
+            depth,
+            anchor_text,
+            source,
+        });
+        self.frontier.push(QueuedVisit { url, depth, score });
+    }
+
+    /// Replace the planner's progress with that of a saved crawl.
+    ///
+    /// Frontier entries that are no longer valid URLs are dropped.
+    pub fn restore(&mut self, state: &mut CrawlState) {
+        self.visited = std::mem::take(&mut state.visited).into_iter().collect();
+        let mut frontier = Vec::new();
+        for v in state.frontier.drain(..) {
+            let Ok(url) = Url::parse(&v.url) else {
+                continue;
+            };
+            if let Some(referrer) = v.referrer.as_deref().and_then(|r| Url::parse(r).ok()) {
+                self.referrers.insert(self.key(&url), referrer);
+            }
+            frontier.push(QueuedVisit {
+                url,
+                depth: v.depth,
+                score: v.score,
+            });
+        }
+        self.frontier.restore(frontier);
+        self.content = ContentIndex::from_fingerprints(std::mem::take(&mut state.fingerprints));
+        self.results = std::mem::take(&mut state.results);
+        self.pages_visited = state.pages_visited;
+        self.pages_skipped = state.pages_skipped;
+    }
+
+    /// Record the planner's progress in `state`.
+    ///
+    /// `pending` are visits handed out by `next_action` but not yet
+    /// processed; they are saved as unvisited, ahead of the frontier, and
+    /// rescored as if they were seeds. Queued next pages are saved the same
+    /// way, so a resumed crawl restarts their series' page count.
+    pub fn save_to(&self, state: &mut CrawlState, pending: &[(Url, usize)]) {
+        let pending_keys: HashSet<String> = pending.iter().map(|(u, _)| self.key(u)).collect();
+        state.visited = self
+            .visited
+            .iter()
+            .filter(|k| !pending_keys.contains(*k))
+            .cloned()
+            .collect();
+        let next_pages = self.next_pages.iter().map(|(url, depth, _)| (url.clone(), *depth));
+        let pending: Vec<(Url, usize)> = pending.iter().cloned().chain(next_pages).collect();
+        let pending = pending.iter().map(|(url, depth)| QueuedVisit {
+            url: url.clone(),
+            depth: *depth,
+            score: self.scorer.score(&LinkCandidate {
+                url,
+                depth: *depth,
+                anchor_text: None,
+                source: None,
+            }),
+        });
+        state.frontier = pending
+            .chain(self.frontier.to_vec())
+            .map(|v| PendingVisit {
+                referrer: self.referrer(&v.url).map(Url::to_string),
+                url: v.url.to_string(),
+                depth: v.depth,
+                score: v.score,
+            })
+            .collect();
+        state.fingerprints = self.content.fingerprints().to_vec();
+        state.results = self.results.clone();
+        state.pages_visited = self.pages_visited;
+        state.pages_skipped = self.pages_skipped;
+    }
+
+    /// The page `url` was first found on, if it was found on a page.
+    pub fn referrer(&self, url: &Url) -> Option<&Url> {
+        self.referrers.get(&self.key(url))
+    }
+
+    pub fn frontier_len(&self) -> usize {
+        self.frontier.len()
     }
 
     pub fn next_action(&mut self) -> AgentAction {
-        while let Some((url, depth)) = self.frontier.pop_front() {
-            let url_str = url.to_string();
-            if self.visited.contains(&url_str) {
+        if let Some((form, depth)) = self.submissions.pop_front() {
+            return AgentAction::Submit(form, depth);
+        }
+        while let Some((url, depth, page)) = self.next_pages.pop_front() {
+            if !self.visited.insert(self.key(&url)) {
+                self.pages_skipped += 1;
+                continue;
+            }
+            return AgentAction::NextPage(url, depth, page);
+        }
+        while let Some(visit) = self.frontier.pop() {
+            if !self.visited.insert(self.key(&visit.url)) {
                 self.pages_skipped += 1;
                 continue;
             }
-            self.visited.insert(url_str);
-            return AgentAction::Visit(url, depth);
+            return AgentAction::Visit(visit.url, visit.depth);
         }
         AgentAction::Done
     }
 
+    /// Add a URL found outside of page links (e.g. in a sitemap) to the
+    /// frontier, subject to the task's link follow patterns.
+    ///
+    /// Returns `false` if the URL was already seen or does not match.
+    pub fn add_discovered(&mut self, url: Url, depth: usize) -> bool {
+        if self.visited.contains(&self.key(&url)) || !self.should_follow(&url) {
+            return false;
+        }
+        self.enqueue(url, depth, None, None);
+        true
+    }
+
+    fn key(&self, url: &Url) -> String {
+        dedup::canonical_key(url, &self.dedup)
+    }
+
+    /// Fingerprint a fetched page's text and check it against the pages
+    /// processed so far.
+    ///
+    /// Returns the URL of an earlier near-duplicate, in which case the page
+    /// is counted as skipped and should not be processed. The page's final
+    /// URL (after redirects) is marked visited either way.
+    pub fn check_duplicate(&mut self, page: &ParsedPage) -> Option<String> {
+        self.visited.insert(self.key(&page.url));
+
+        let max_distance = self.dedup.near_duplicate_distance?;
+        let fingerprint = dedup::simhash(&page.text_content);
+        if let Some(original) = self.content.find_near(fingerprint, max_distance) {
+            self.pages_skipped += 1;
+            return Some(original.to_string());
+        }
+        self.content.insert(page.url.to_string(), fingerprint);
+        None
+    }
+
+    /// The next page after `page` if it is part of a paginated series.
+    fn next_page(&self, page: &ParsedPage) -> Option<Url> {
+        let pagination = self.task.pagination.as_ref()?;
+        let key = self.key(&page.url);
+        let number = match self.page_numbers.get(&key) {
+            Some(number) => *number,
+            // Not reached by pagination: only pages matching the pattern,
+            // or without one the seeds, start a new series.
+            None => {
+                let starts = match &pagination.page_pattern {
+                    Some(pattern) => pattern.0.is_match(page.url.as_str()),
+                    None => self.task.seed_urls.iter().any(|seed| self.key(seed) == key),
+                };
+                if !starts {
+                    return None;
+                }
+                1
+            }
+        };
+        pagination::next_page(page, pagination, number)
+    }
+
+    fn should_follow(&self, url: &Url) -> bool {
+        let included = self.task.link_follow_patterns.is_empty()
+            || self
+                .task
+                .link_follow_patterns
+                .iter()
+                .any(|p| p.is_match(url.as_str()));
+
+        included
+            && !self
+                .task
+                .link_exclude_patterns
+                .iter()
+                .any(|p| p.is_match(url.as_str()))
+    }
+
     pub fn process_page(&mut self, page: &ParsedPage, depth: usize) {
         self.pages_visited += 1;
 
         let items = extractor::extract(page, &self.task.goal);
         self.results.extend(items);
 
+        // The next page of a series is visited as a page, not followed as
+        // a link one level deeper.
+        let next_key = self.next_page(page).map(|url| {
+            let key = self.key(&url);
+            if !self.visited.contains(&key) {
+                self.referrers.insert(key.clone(), page.url.clone());
+                let number = self.page_numbers.get(&self.key(&page.url)).copied().unwrap_or(1) + 1;
+                self.page_numbers.insert(key.clone(), number);
+                self.next_pages.push_back((url, depth, number));
+            }
+            key
+        });
+
         for link in &page.links {
-            let link_str = link.to_string();
-            if self.visited.contains(&link_str) {
+            let key = self.key(link);
+            if self.visited.contains(&key) || Some(&key) == next_key.as_ref() {
                 continue;
             }
 
-            let should_follow = self.task.link_follow_patterns.is_empty()
-                || self
-                    .task
-                    .link_follow_patterns
-                    .iter()
-                    .any(|p| p.is_match(link.as_str()));
+            if self.should_follow(link) {
+                let anchor_text = page.link_text.get(link.as_str()).map(String::as_str);
+                self.enqueue(link.clone(), depth + 1, anchor_text, Some(&page.url));
+            }
+        }
 
-            if should_follow {
-                self.frontier.push_back((link.clone(), depth + 1));
+        for spec in &self.task.forms {
+            if spec
+                .page_pattern
+                .as_ref()
+                .is_some_and(|p| !p.0.is_match(page.url.as_str()))
+            {
+                continue;
+            }
+            for form in forms::select(page, &spec.selector) {
+                let request = form.fill(&spec.values);
+                if self.submitted.insert(request.key()) {
+                    self.referrers
+                        .entry(self.key(&request.url))
+                        .or_insert_with(|| page.url.clone());
+                    self.submissions.push_back((request, depth + 1));
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::{Browser, PageContent};
+    use crate::config::ContentKind;
+    use crate::task::{ExtractionGoal, FormSubmission, Pagination};
+    use crate::transform::Pattern;
+    use regex::Regex;
+
+    fn task(pagination: Option<Pagination>, forms: Vec<FormSubmission>) -> Task {
+        Task {
+            name: "plan".to_string(),
+            seed_urls: vec![Url::parse("https://example.com/list").unwrap()],
+            goal: ExtractionGoal::CollectLinks,
+            link_follow_patterns: vec![],
+            link_exclude_patterns: vec![],
+            discover_sitemaps: false,
+            forms,
+            pagination,
+        }
+    }
+
+    fn planner(task: Task) -> Planner {
+        Planner::new(task, DedupConfig::default(), FrontierConfig::default())
+    }
+
+    fn page(url: &str, html: &str) -> ParsedPage {
+        Browser::parse(&PageContent {
+            url: Url::parse(url).unwrap(),
+            status: 200,
+            kind: ContentKind::Html,
+            body: html.to_string(),
+            retry_after: None,
+        })
+    }
+
+    /// The actions the planner hands out until it runs dry, as strings.
+    fn drain(planner: &mut Planner) -> Vec<String> {
+        let mut actions = Vec::new();
+        loop {
+            actions.push(match planner.next_action() {
+                AgentAction::Visit(url, depth) => format!("visit {} {}", url, depth),
+                AgentAction::Submit(form, depth) => format!("submit {} {}", form.key(), depth),
+                AgentAction::NextPage(url, depth, page) => {
+                    format!("page {} {} {}", page, url, depth)
+                }
+                AgentAction::Done => return actions,
+            });
+        }
+    }
+
+    #[test]
+    fn test_pagination_starts_on_seeds_only() {
+        let mut planner = planner(task(Some(Pagination::default()), vec![]));
+        assert_eq!(drain(&mut planner), ["visit https://example.com/list 0"]);
+
+        let next = r#"<link rel="next" href="?page=2"><a href="/other">Other</a>"#;
+        planner.process_page(&page("https://example.com/list", next), 0);
+        planner.process_page(&page("https://example.com/other", next), 1);
+        assert_eq!(
+            drain(&mut planner),
+            [
+                "page 2 https://example.com/list?page=2 0",
+                "visit https://example.com/other 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_url_template_follows_page_pattern() {
+        let pagination = Pagination {
+            url_template: Some("?page={page}".to_string()),
+            max_pages: 3,
+            page_pattern: Some(Pattern(Regex::new(r"/list$").unwrap())),
+            ..Default::default()
+        };
+        let mut planner = planner(task(Some(pagination), vec![]));
+        planner.next_action();
+
+        planner.process_page(&page("https://example.com/list", r#"<a href="/a">A</a>"#), 0);
+        planner.process_page(&page("https://example.com/a", ""), 1);
+        assert_eq!(
+            drain(&mut planner),
+            ["page 2 https://example.com/list?page=2 0", "visit https://example.com/a 1"]
+        );
+        planner.process_page(&page("https://example.com/list?page=2", ""), 0);
+        assert_eq!(drain(&mut planner), ["page 3 https://example.com/list?page=3 0"]);
+        // The series stops at max_pages.
+        planner.process_page(&page("https://example.com/list?page=3", ""), 0);
+        assert!(drain(&mut planner).is_empty());
+    }
+
+    #[test]
+    fn test_forms_are_submitted_once_ahead_of_links() {
+        let search = FormSubmission {
+            selector: "form.search".to_string(),
+            page_pattern: None,
+            values: [("q".to_string(), "rust".to_string())].into(),
+        };
+        let mut planner = planner(task(None, vec![search]));
+        planner.next_action();
+
+        let html = r#"<a href="/a">A</a>
+            <form class="search" action="/search"><input name="q"><input name="lang" value="en"></form>
+            <form action="/login" method="post"><input name="user"></form>"#;
+        planner.process_page(&page("https://example.com/list", html), 0);
+        // The same form on another page is not submitted again.
+        planner.process_page(&page("https://example.com/a", html), 1);
+        assert_eq!(
+            drain(&mut planner),
+            [
+                "submit GET https://example.com/search?q=rust&lang=en  1",
+                "visit https://example.com/a 1",
+            ]
+        );
+        let search = Url::parse("https://example.com/search?q=rust&lang=en").unwrap();
+        assert_eq!(planner.referrer(&search).unwrap().as_str(), "https://example.com/list");
+    }
+
+    #[test]
+    fn test_urls_are_deduplicated_by_canonical_key() {
+        let mut planner = planner(task(None, vec![]));
+        planner.next_action();
+
+        let html = r#"<a href="/a">A</a> <a href="/a/#top">A</a>
+            <a href="/a?utm_source=mail">A</a> <a href="/b?y=2&x=1">B</a>
+            <a href="/b?x=1&y=2">B</a> <a href="/list/">Home</a>"#;
+        planner.process_page(&page("https://example.com/list", html), 0);
+        assert_eq!(
+            drain(&mut planner),
+            ["visit https://example.com/a 1", "visit https://example.com/b?y=2&x=1 1"]
+        );
+        assert_eq!(planner.pages_skipped, 3);
+    }
+
+    #[test]
+    fn test_templated_pages_are_not_dropped_by_default() {
+        let listing = |n: usize| {
+            let html = format!(
+                r#"<nav>Home Shop About Contact</nav><h1>Shoes</h1>
+                   <a href="/shoe/{n}">Shoe</a><a href="?page={next}">Next</a>"#,
+                n = n,
+                next = n + 1
+            );
+            page(&format!("https://example.com/list?page={}", n), &html)
+        };
+
+        let mut planner = planner(task(None, vec![]));
+        for n in 1..=3 {
+            assert_eq!(planner.check_duplicate(&listing(n)), None);
+        }
+        assert_eq!(planner.pages_skipped, 0);
+
+        // Opting in to near-duplicate skipping drops them as one page.
+        let dedup = DedupConfig {
+            near_duplicate_distance: Some(3),
+            ..Default::default()
+        };
+        let mut planner = Planner::new(task(None, vec![]), dedup, FrontierConfig::default());
+        assert_eq!(planner.check_duplicate(&listing(1)), None);
+        assert_eq!(
+            planner.check_duplicate(&listing(2)).as_deref(),
+            Some("https://example.com/list?page=1")
+        );
+    }
+
+    #[test]
+    fn test_save_and_restore_frontier() {
+        let mut first = planner(task(None, vec![]));
+        first.next_action();
+        let html = r#"<a href="/a">A</a> <a href="/b">B</a>"#;
+        first.process_page(&page("https://example.com/list", html), 0);
+        // `/a` is handed out but not yet processed when the crawl stops.
+        let AgentAction::Visit(pending, depth) = first.next_action() else {
+            panic!("expected a visit");
+        };
+        let mut state = CrawlState::default();
+        first.save_to(&mut state, &[(pending, depth)]);
+
+        let mut resumed = planner(task(None, vec![]));
+        resumed.restore(&mut state);
+        assert_eq!(resumed.pages_visited, 1);
+        assert_eq!(
+            drain(&mut resumed),
+            ["visit https://example.com/a 1", "visit https://example.com/b 1"]
+        );
+        let b = Url::parse("https://example.com/b").unwrap();
+        assert_eq!(resumed.referrer(&b).unwrap().as_str(), "https://example.com/list");
+    }
+}
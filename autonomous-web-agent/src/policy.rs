@@ -0,0 +1,389 @@
+use crate::config::{PolicyRule, RuleAction, SafetyConfig};
+use crate::error::{AgentError, Result};
+use ipnet::IpNet;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use url::{Host, Url};
+
+/// The outcome of checking a URL against the policy, with the rule that
+/// decided it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    pub url: String,
+    pub action: RuleAction,
+    /// The rule's name: a configured rule's `name` or `rules[<index>]`, or
+    /// one of the built-in `private_address`, `allowed_domains` and
+    /// `default`.
+    pub rule: String,
+    pub reason: String,
+}
+
+/// A compiled `PolicyConfig`, plus the `allowed_domains` allowlist.
+pub struct Policy {
+    rules: Vec<Rule>,
+    default_action: RuleAction,
+    block_private_addresses: bool,
+    allowed_private_ranges: Vec<IpNet>,
+    allowed_domains: Vec<String>,
+}
+
+struct Rule {
+    name: String,
+    action: RuleAction,
+    schemes: Vec<String>,
+    hosts: Vec<String>,
+    paths: Vec<Regex>,
+    query_params: Vec<(String, Option<String>)>,
+    ports: Vec<u16>,
+    ip_ranges: Vec<IpNet>,
+}
+
+impl Policy {
+    /// Compile the policy. Fails on an invalid IP range rather than
+    /// dropping it, since that would widen the rule it belongs to.
+    pub fn new(config: &SafetyConfig) -> Result<Self> {
+        let policy = &config.policy;
+        let rules = policy
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| Rule::new(rule, i))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            rules,
+            default_action: policy.default_action,
+            block_private_addresses: policy.block_private_addresses,
+            allowed_private_ranges: parse_ranges(&policy.allowed_private_ranges)?,
+            allowed_domains: config.allowed_domains.clone(),
+        })
+    }
+
+    /// Whether `evaluate` needs the addresses a host name resolves to.
+    pub fn needs_addresses(&self) -> bool {
+        self.block_private_addresses || self.rules.iter().any(|r| !r.ip_ranges.is_empty())
+    }
+
+    /// Whether the agent may connect to `ip`.
+    pub fn allows_address(&self, ip: IpAddr) -> bool {
+        !self.block_private_addresses
+            || is_public(ip)
+            || self.allowed_private_ranges.iter().any(|r| r.contains(&ip))
+    }
+
+    /// Check a URL. `addrs` are the addresses its host resolves to; a
+    /// literal IP host is checked without them.
+    pub fn evaluate(&self, url: &Url, addrs: &[IpAddr]) -> PolicyDecision {
+        let decision = |action, rule: &str, reason: String| PolicyDecision {
+            url: url.to_string(),
+            action,
+            rule: rule.to_string(),
+            reason,
+        };
+
+        let mut addrs = addrs.to_vec();
+        match url.host() {
+            Some(Host::Ipv4(ip)) => addrs.push(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => addrs.push(IpAddr::V6(ip)),
+            _ => {}
+        }
+
+        if let Some(ip) = addrs.iter().find(|ip| !self.allows_address(**ip)) {
+            return decision(
+                RuleAction::Deny,
+                "private_address",
+                format!("Non-public address {}: {}", ip, url),
+            );
+        }
+
+        if let Some(rule) = self.rules.iter().find(|rule| rule.matches(url, &addrs)) {
+            let reason = match rule.action {
+                RuleAction::Allow => format!("Allowed by rule '{}'", rule.name),
+                RuleAction::Deny => format!("Denied by rule '{}': {}", rule.name, url),
+            };
+            return decision(rule.action, &rule.name, reason);
+        }
+
+        let domain = url.host_str().unwrap_or("");
+        if !self.allowed_domains.is_empty() {
+            return if domain_matches(domain, &self.allowed_domains) {
+                decision(
+                    RuleAction::Allow,
+                    "allowed_domains",
+                    format!("Domain '{}' in allowlist", domain),
+                )
+            } else {
+                decision(
+                    RuleAction::Deny,
+                    "allowed_domains",
+                    format!("Domain '{}' not in allowlist", domain),
+                )
+            };
+        }
+
+        let reason = match self.default_action {
+            RuleAction::Allow => "Allowed by default".to_string(),
+            RuleAction::Deny => format!("Denied by default: {}", url),
+        };
+        decision(self.default_action, "default", reason)
+    }
+}
+
+impl Rule {
+    fn new(rule: &PolicyRule, index: usize) -> Result<Self> {
+        Ok(Self {
+            name: rule
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("rules[{}]", index)),
+            action: rule.action,
+            schemes: rule
+                .schemes
+                .iter()
+                .map(|s| s.to_ascii_lowercase())
+                .collect(),
+            hosts: rule.hosts.iter().map(|h| h.to_ascii_lowercase()).collect(),
+            paths: rule.paths.iter().map(|p| glob_to_regex(p)).collect(),
+            query_params: rule
+                .query_params
+                .iter()
+                .map(|p| match p.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None => (p.clone(), None),
+                })
+                .collect(),
+            ports: rule.ports.clone(),
+            ip_ranges: parse_ranges(&rule.ip_ranges)?,
+        })
+    }
+
+    fn matches(&self, url: &Url, addrs: &[IpAddr]) -> bool {
+        let host = url.host_str().unwrap_or("");
+        let port = url.port_or_known_default();
+        condition(&self.schemes, |s| s == url.scheme())
+            && (self.hosts.is_empty() || domain_matches(host, &self.hosts))
+            && condition(&self.paths, |p| p.is_match(url.path()))
+            && condition(&self.query_params, |(name, value)| {
+                url.query_pairs()
+                    .any(|(n, v)| n == *name && value.as_ref().is_none_or(|value| v == *value))
+            })
+            && condition(&self.ports, |p| port == Some(*p))
+            && condition(&self.ip_ranges, |r| addrs.iter().any(|ip| r.contains(ip)))
+    }
+}
+
+/// An unset condition matches; a set one matches when any value does.
+fn condition<T>(values: &[T], matches: impl Fn(&T) -> bool) -> bool {
+    values.is_empty() || values.iter().any(matches)
+}
+
+/// Parse CIDR ranges; a bare address is a range of one.
+fn parse_ranges(ranges: &[String]) -> Result<Vec<IpNet>> {
+    ranges
+        .iter()
+        .map(|r| {
+            r.parse::<IpNet>()
+                .or_else(|_| r.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| AgentError::Policy(format!("invalid IP range '{}'", r)))
+        })
+        .collect()
+}
+
+/// Compile a path glob to an anchored regex.
+fn glob_to_regex(glob: &str) -> Regex {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).expect("escaped glob is a valid regex")
+}
+
+/// Whether `domain` is one of `domains` or a subdomain of one.
+pub(crate) fn domain_matches(domain: &str, domains: &[String]) -> bool {
+    domains
+        .iter()
+        .any(|d| domain == d.as_str() || domain.ends_with(&format!(".{}", d)))
+}
+
+/// Whether `ip` is a publicly routable address. Private, loopback,
+/// link-local, shared (CGNAT), documentation, benchmarking, multicast and
+/// reserved ranges are not, nor are IPv4-mapped IPv6 forms of them.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public_v4(v4),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || a >= 240
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (b == 18 || b == 19)))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local fc00::/7 and link-local fe80::/10.
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        // Documentation 2001:db8::/32.
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PolicyConfig;
+
+    fn rule(action: RuleAction) -> PolicyRule {
+        PolicyRule {
+            name: None,
+            action,
+            schemes: vec![],
+            hosts: vec![],
+            paths: vec![],
+            query_params: vec![],
+            ports: vec![],
+            ip_ranges: vec![],
+        }
+    }
+
+    fn policy(rules: Vec<PolicyRule>, allowed_domains: &[&str]) -> Policy {
+        Policy::new(&SafetyConfig {
+            allowed_domains: allowed_domains.iter().map(|d| d.to_string()).collect(),
+            policy: PolicyConfig {
+                rules,
+                allowed_private_ranges: vec!["127.0.0.1".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn check(policy: &Policy, url: &str) -> PolicyDecision {
+        policy.evaluate(&Url::parse(url).unwrap(), &[])
+    }
+
+    #[test]
+    fn test_private_addresses() {
+        let policy = policy(vec![rule(RuleAction::Allow)], &[]);
+        for url in [
+            "http://10.0.0.5/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]:8080/",
+            "http://[::ffff:192.168.1.1]/",
+            "http://0.0.0.0/",
+        ] {
+            let decision = check(&policy, url);
+            assert_eq!(decision.action, RuleAction::Deny, "{}", url);
+            assert_eq!(decision.rule, "private_address");
+        }
+        // Exempted, then allowed by the catch-all rule.
+        assert_eq!(check(&policy, "http://127.0.0.1:8765/").rule, "rules[0]");
+
+        // Resolved addresses count too.
+        let url = Url::parse("https://internal.example.com/").unwrap();
+        let decision = policy.evaluate(&url, &["192.168.0.10".parse().unwrap()]);
+        assert_eq!(decision.action, RuleAction::Deny);
+        assert!(is_public("93.184.216.34".parse().unwrap()));
+        assert!(!is_public("100.64.1.1".parse().unwrap()));
+        assert!(!is_public("fd12::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_rules_in_order() {
+        let mut admin = rule(RuleAction::Deny);
+        admin.name = Some("no-admin".to_string());
+        admin.paths = vec!["/admin/**".to_string()];
+        let mut sessions = rule(RuleAction::Deny);
+        sessions.query_params = vec!["session".to_string(), "debug=1".to_string()];
+        let mut plain_http = rule(RuleAction::Deny);
+        plain_http.schemes = vec!["http".to_string()];
+        plain_http.ports = vec![80];
+        let mut docs = rule(RuleAction::Allow);
+        docs.hosts = vec!["docs.example.org".to_string()];
+        docs.paths = vec!["/v?/*.html".to_string()];
+        let policy = policy(vec![admin, sessions, plain_http, docs], &["example.com"]);
+
+        let denied = check(&policy, "https://example.com/admin/users/1");
+        assert_eq!(
+            (denied.action, denied.rule.as_str()),
+            (RuleAction::Deny, "no-admin")
+        );
+        assert_eq!(
+            check(&policy, "https://example.com/a?session=x").rule,
+            "rules[1]"
+        );
+        assert_eq!(
+            check(&policy, "https://example.com/a?debug=1").rule,
+            "rules[1]"
+        );
+        assert_eq!(
+            check(&policy, "https://example.com/a?debug=0").rule,
+            "allowed_domains"
+        );
+        assert_eq!(check(&policy, "http://example.com/").rule, "rules[2]");
+        assert_eq!(
+            check(&policy, "http://example.com:8080/").action,
+            RuleAction::Allow
+        );
+
+        let docs = check(&policy, "https://api.docs.example.org/v2/intro.html");
+        assert_eq!(
+            (docs.action, docs.rule.as_str()),
+            (RuleAction::Allow, "rules[3]")
+        );
+        // `*` does not cross path segments.
+        let nested = check(&policy, "https://docs.example.org/v2/a/b.html");
+        assert_eq!(
+            (nested.action, nested.rule.as_str()),
+            (RuleAction::Deny, "allowed_domains")
+        );
+    }
+
+    #[test]
+    fn test_ip_range_rules() {
+        let mut cloud = rule(RuleAction::Deny);
+        cloud.ip_ranges = vec!["203.0.113.0/24".to_string()];
+        let mut config = SafetyConfig::default();
+        config.policy.rules = vec![cloud];
+        config.policy.block_private_addresses = false;
+        let policy = Policy::new(&config).unwrap();
+
+        let url = Url::parse("https://cdn.example.com/").unwrap();
+        let decision = policy.evaluate(&url, &["203.0.113.7".parse().unwrap()]);
+        assert_eq!(decision.action, RuleAction::Deny);
+        assert_eq!(policy.evaluate(&url, &[]).rule, "default");
+        assert_eq!(check(&policy, "http://10.0.0.1/").action, RuleAction::Allow);
+
+        config.policy.allowed_private_ranges = vec!["10.0.0.0/33".to_string()];
+        assert!(Policy::new(&config).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write;
+
+/// What the agent did with a URL it took from the frontier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// Fetched, parsed and run through the extractor.
+    Processed,
+    /// Rejected by the URL, robots.txt or form guardrails; not fetched.
+    Blocked,
+    /// Deeper than the configured limit; not fetched.
+    DepthExceeded,
+    /// Its host or path prefix had used up its share of the budget; not
+    /// fetched.
+    OverBudget,
+    /// Fetched, but rejected by the content guardrails.
+    ContentBlocked,
+    /// Too large, or of a content type the task does not process; its body
+    /// was not downloaded.
+    ContentSkipped,
+    /// Fetched, but its text nearly matches an earlier page's.
+    NearDuplicate,
+    /// Answered with a non-200 status.
+    HttpError,
+    /// The request failed.
+    FetchFailed,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Decision::Processed => "processed",
+            Decision::Blocked => "blocked",
+            Decision::DepthExceeded => "depth_exceeded",
+            Decision::OverBudget => "over_budget",
+            Decision::ContentBlocked => "content_blocked",
+            Decision::ContentSkipped => "content_skipped",
+            Decision::NearDuplicate => "near_duplicate",
+            Decision::HttpError => "http_error",
+            Decision::FetchFailed => "fetch_failed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The audit record of one URL the agent considered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageRecord {
+    pub url: String,
+    /// The page the URL was first found on; `None` for seeds and sitemap
+    /// entries.
+    pub referrer: Option<String>,
+    pub depth: usize,
+    pub decision: Decision,
+    /// Why the page was not processed.
+    pub reason: Option<String>,
+    pub status: Option<u16>,
+    /// Duration of the last fetch attempt, excluding rate-limit waits.
+    pub fetch_ms: Option<u64>,
+    /// Size of the decoded body.
+    pub bytes: Option<usize>,
+    pub title: Option<String>,
+    /// Number of items extracted from the page.
+    pub items: usize,
+    /// Requests made for the URL, including retries.
+    pub attempts: u32,
+}
+
+/// A link found on a processed page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    pub from: String,
+    pub to: String,
+}
+
+/// Nodes of the link graph: every recorded page, then every link target
+/// that was never visited, in first-seen order.
+fn nodes<'a>(pages: &'a [PageRecord], links: &'a [Link]) -> Vec<(&'a str, Option<&'a PageRecord>)> {
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    let mut nodes = Vec::new();
+    for page in pages {
+        // A URL can be recorded twice, e.g. as a page and as the target of
+        // a GET form; the latest record wins.
+        match index.get(page.url.as_str()) {
+            Some(&i) => nodes[i] = (page.url.as_str(), Some(page)),
+            None => {
+                index.insert(&page.url, nodes.len());
+                nodes.push((page.url.as_str(), Some(page)));
+            }
+        }
+    }
+    for url in links.iter().flat_map(|l| [l.from.as_str(), l.to.as_str()]) {
+        if !index.contains_key(url) {
+            index.insert(url, nodes.len());
+            nodes.push((url, None));
+        }
+    }
+    nodes
+}
+
+/// The link graph in Graphviz DOT format. Processed pages are drawn solid,
+/// pages that were not processed red, and links never visited dashed.
+pub fn to_dot(pages: &[PageRecord], links: &[Link]) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let quote = |s: &str| format!("\"{}\"", escape(s));
+
+    let mut dot = String::from("digraph crawl {\n    node [shape=box];\n");
+    for (url, page) in nodes(pages, links) {
+        let attrs = match page {
+            Some(page) if page.decision == Decision::Processed => format!(
+                "label={}, tooltip={}",
+                quote(page.title.as_deref().unwrap_or(url)),
+                quote(url)
+            ),
+            Some(page) => format!(
+                "label=\"{}\\n{}\", color=red, tooltip={}",
+                escape(url),
+                page.decision,
+                quote(page.reason.as_deref().unwrap_or(url))
+            ),
+            None => format!("label={}, style=dashed", quote(url)),
+        };
+        let _ = writeln!(dot, "    {} [{}];", quote(url), attrs);
+    }
+    for link in links {
+        let _ = writeln!(dot, "    {} -> {};", quote(&link.from), quote(&link.to));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The link graph in GraphML, with each node's URL and, for visited
+/// pages, its audit record as node data.
+pub fn to_graphml(pages: &[PageRecord], links: &[Link]) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+         \x20 <key id=\"url\" for=\"node\" attr.name=\"url\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"decision\" for=\"node\" attr.name=\"decision\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"depth\" for=\"node\" attr.name=\"depth\" attr.type=\"int\"/>\n\
+         \x20 <key id=\"status\" for=\"node\" attr.name=\"status\" attr.type=\"int\"/>\n\
+         \x20 <key id=\"title\" for=\"node\" attr.name=\"title\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"items\" for=\"node\" attr.name=\"items\" attr.type=\"int\"/>\n\
+         \x20 <graph id=\"crawl\" edgedefault=\"directed\">\n",
+    );
+
+    let nodes = nodes(pages, links);
+    let ids: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, (url, _))| (*url, i))
+        .collect();
+    for (i, (url, page)) in nodes.iter().enumerate() {
+        let _ = writeln!(xml, "    <node id=\"n{}\">", i);
+        let _ = writeln!(xml, "      <data key=\"url\">{}</data>", escape(url));
+        if let Some(page) = page {
+            let _ = writeln!(xml, "      <data key=\"decision\">{}</data>", page.decision);
+            let _ = writeln!(xml, "      <data key=\"depth\">{}</data>", page.depth);
+            if let Some(status) = page.status {
+                let _ = writeln!(xml, "      <data key=\"status\">{}</data>", status);
+            }
+            if let Some(title) = &page.title {
+                let _ = writeln!(xml, "      <data key=\"title\">{}</data>", escape(title));
+            }
+            let _ = writeln!(xml, "      <data key=\"items\">{}</data>", page.items);
+        }
+        xml.push_str("    </node>\n");
+    }
+    for link in links {
+        let _ = writeln!(
+            xml,
+            "    <edge source=\"n{}\" target=\"n{}\"/>",
+            ids[link.from.as_str()],
+            ids[link.to.as_str()]
+        );
+    }
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(url: &str, decision: Decision) -> PageRecord {
+        PageRecord {
+            url: url.to_string(),
+            referrer: None,
+            depth: 0,
+            decision,
+            reason: None,
+            status: Some(200),
+            fetch_ms: Some(12),
+            bytes: Some(100),
+            title: Some("Home & \"more\"".to_string()),
+            items: 2,
+            attempts: 1,
+        }
+    }
+
+    fn graph() -> (Vec<PageRecord>, Vec<Link>) {
+        let pages = vec![
+            record("https://a.test/", Decision::Processed),
+            record("https://a.test/private", Decision::Blocked),
+        ];
+        let links = ["https://a.test/private", "https://b.test/"]
+            .iter()
+            .map(|to| Link {
+                from: "https://a.test/".to_string(),
+                to: to.to_string(),
+            })
+            .collect();
+        (pages, links)
+    }
+
+    #[test]
+    fn test_dot() {
+        let (pages, links) = graph();
+        let dot = to_dot(&pages, &links);
+        assert!(dot.starts_with("digraph crawl {"));
+        assert!(dot.contains(r#""https://a.test/" [label="Home & \"more\"""#));
+        assert!(dot.contains("color=red"));
+        assert!(dot.contains(r#""https://b.test/" [label="https://b.test/", style=dashed];"#));
+        assert!(dot.contains(r#""https://a.test/" -> "https://b.test/";"#));
+    }
+
+    #[test]
+    fn test_graphml() {
+        let (pages, links) = graph();
+        let xml = to_graphml(&pages, &links);
+        assert!(xml.contains("<data key=\"title\">Home &amp; &quot;more&quot;</data>"));
+        assert!(xml.contains("<data key=\"decision\">blocked</data>"));
+        assert!(xml.contains("<edge source=\"n0\" target=\"n1\"/>"));
+        assert!(xml.contains("<edge source=\"n0\" target=\"n2\"/>"));
+        assert_eq!(xml.matches("<node ").count(), 3);
+    }
+}
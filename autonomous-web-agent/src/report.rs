@@ -1,4 +1,9 @@
 use crate::extractor::ExtractedItem;
+use crate::guardrails::Budget;
+use crate::policy::PolicyDecision;
+use crate::provenance::{self, Link, PageRecord};
+use crate::redact::RedactionStats;
+use crate::retry::FetchError;
 use colored::Colorize;
 use serde::Serialize;
 use std::time::Duration;
@@ -6,10 +11,27 @@ use std::time::Duration;
 #[derive(Debug, Serialize)]
 pub struct Report {
     pub task_name: String,
+    /// Identifies the run. A resumed crawl keeps the id of the run it
+    /// continues, so its output can be told apart from a separate run's.
+    pub run_id: String,
     pub items: Vec<ExtractedItem>,
     pub pages_visited: usize,
     pub pages_skipped: usize,
+    /// The budget that stopped the crawl, if one did.
+    pub budget_exhausted: Option<Budget>,
+    /// Total size of the response bodies received.
+    pub bytes_downloaded: u64,
     pub safety_blocks: Vec<String>,
+    /// URLs the URL policy denied, with the rule that denied them.
+    pub policy_denials: Vec<PolicyDecision>,
+    /// Sensitive data masked in, or dropped from, `items`.
+    pub redactions: RedactionStats,
+    /// URLs that could not be fetched, after any retries.
+    pub errors: Vec<FetchError>,
+    /// What the agent did with every URL it took from the frontier.
+    pub pages: Vec<PageRecord>,
+    /// Links found on processed pages.
+    pub links: Vec<Link>,
     #[serde(skip)]
     pub duration: Duration,
 }
@@ -35,16 +57,41 @@ impl Report {
             "📦".white(),
             self.items.len().to_string().green()
         );
+        if self.redactions.total() > 0 {
+            println!(
+                "{}  Redactions: {} ({} items dropped)",
+                "🔒".white(),
+                self.redactions.total().to_string().yellow(),
+                self.redactions.items_dropped
+            );
+        }
         println!(
             "{}  Safety blocks: {}",
             "🛡️ ".white(),
             self.safety_blocks.len().to_string().red()
         );
+        println!(
+            "{}  Fetch errors: {}",
+            "⚠️ ".white(),
+            self.errors.len().to_string().red()
+        );
+        println!(
+            "{}  Downloaded: {} bytes",
+            "📥".white(),
+            self.bytes_downloaded
+        );
         println!(
             "{}  Duration: {:.2}s",
             "⏱️ ".white(),
             self.duration.as_secs_f64()
         );
+        if let Some(budget) = self.budget_exhausted {
+            println!(
+                "{}  Stopped: {} budget exhausted",
+                "⛔".white(),
+                budget.to_string().red()
+            );
+        }
 
         if !self.safety_blocks.is_empty() {
             println!("\n{}", "Safety Events:".red().bold());
@@ -53,6 +100,20 @@ impl Report {
             }
         }
 
+        if !self.errors.is_empty() {
+            println!("\n{}", "Fetch Errors:".red().bold());
+            for error in &self.errors {
+                println!(
+                    "  {} {} ({}, {} attempt{})",
+                    "▸".red(),
+                    error.url,
+                    error.message,
+                    error.attempts,
+                    if error.attempts == 1 { "" } else { "s" }
+                );
+            }
+        }
+
         if !self.items.is_empty() {
             println!("\n{}", "Extracted Data:".green().bold());
             let display_count = self.items.len().min(20);
@@ -61,7 +122,7 @@ impl Report {
                     "  {} [{}] {}",
                     "▸".green(),
                     item.field.dimmed(),
-                    truncate(&item.value, 100)
+                    truncate(&item.value_text(), 100)
                 );
             }
             if self.items.len() > display_count {
@@ -79,12 +140,93 @@ impl Report {
     pub fn to_json(&self) -> String {
         serde_json::to_string_pretty(self).unwrap_or_default()
     }
+
+    /// The link graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        provenance::to_dot(&self.pages, &self.links)
+    }
+
+    /// The link graph in GraphML format.
+    pub fn to_graphml(&self) -> String {
+        provenance::to_graphml(&self.pages, &self.links)
+    }
 }
 
+/// The first `max_len` characters of `s`, followed by `...` if it was cut.
 fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len])
+    match s.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}...", &s[..end]),
+        None => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::FetchErrorKind;
+    use serde_json::{json, Value};
+
+    fn report() -> Report {
+        Report {
+            task_name: "docs".to_string(),
+            run_id: "0123456789abcdef".to_string(),
+            items: vec![ExtractedItem {
+                source_url: "https://example.com/".to_string(),
+                field: "h1".to_string(),
+                value: json!("Welcome"),
+            }],
+            pages_visited: 2,
+            pages_skipped: 1,
+            budget_exhausted: Some(Budget::Bytes),
+            bytes_downloaded: 2048,
+            safety_blocks: vec![],
+            policy_denials: vec![],
+            redactions: RedactionStats::default(),
+            errors: vec![FetchError {
+                url: "https://example.com/down".to_string(),
+                kind: FetchErrorKind::HttpStatus { status: 503 },
+                message: "HTTP 503".to_string(),
+                attempts: 3,
+            }],
+            pages: vec![],
+            links: vec![Link {
+                from: "https://example.com/".to_string(),
+                to: "https://example.com/down".to_string(),
+            }],
+            duration: Duration::from_secs(2),
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json: Value = serde_json::from_str(&report().to_json()).unwrap();
+        assert_eq!(json["run_id"], "0123456789abcdef");
+        assert_eq!(json["budget_exhausted"], "bytes");
+        assert_eq!(json["items"][0]["value"], "Welcome");
+        assert_eq!(
+            json["errors"][0],
+            json!({
+                "url": "https://example.com/down",
+                "kind": "http_status",
+                "status": 503,
+                "message": "HTTP 503",
+                "attempts": 3,
+            })
+        );
+        assert!(json.get("duration").is_none());
+    }
+
+    #[test]
+    fn test_link_graph() {
+        let dot = report().to_dot();
+        assert!(dot.contains(r#""https://example.com/" -> "https://example.com/down";"#));
+        assert!(report().to_graphml().contains("<graphml"));
+    }
+
+    #[test]
+    fn test_truncate_keeps_whole_characters() {
+        assert_eq!(truncate("Ünïcödé", 7), "Ünïcödé");
+        assert_eq!(truncate("Ünïcödé text", 3), "Ünï...");
+        assert_eq!(truncate("€€€€", 1), "€...");
     }
 }
@@ -0,0 +1,195 @@
+use crate::config::RetryConfig;
+use crate::error::AgentError;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use std::time::{Duration, SystemTime};
+
+/// Why a fetch failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FetchErrorKind {
+    /// The host name could not be resolved.
+    Dns,
+    /// The TLS handshake or certificate validation failed.
+    Tls,
+    /// The request timed out.
+    Timeout,
+    /// The connection could not be established or was reset.
+    Connect,
+    /// The server answered with a non-success status.
+    HttpStatus { status: u16 },
+    Other,
+}
+
+impl FetchErrorKind {
+    /// Classify an error returned by the browser.
+    pub fn classify(error: &AgentError) -> Self {
+        let AgentError::Http(e) = error else {
+            return FetchErrorKind::Other;
+        };
+        if e.is_timeout() {
+            return FetchErrorKind::Timeout;
+        }
+        if let Some(status) = e.status() {
+            return FetchErrorKind::HttpStatus {
+                status: status.as_u16(),
+            };
+        }
+
+        // reqwest does not expose DNS and TLS failures directly; they are
+        // recognized from the messages of the underlying errors.
+        let mut chain = String::new();
+        let mut source: Option<&dyn StdError> = Some(e);
+        while let Some(err) = source {
+            chain.push_str(&err.to_string().to_lowercase());
+            chain.push('\n');
+            source = err.source();
+        }
+
+        if ["dns error", "failed to lookup address", "name or service not known", "no such host"]
+            .iter()
+            .any(|s| chain.contains(s))
+        {
+            FetchErrorKind::Dns
+        } else if ["certificate", "tls", "ssl", "handshake"]
+            .iter()
+            .any(|s| chain.contains(s))
+        {
+            FetchErrorKind::Tls
+        } else if e.is_connect() || e.is_request() {
+            FetchErrorKind::Connect
+        } else {
+            FetchErrorKind::Other
+        }
+    }
+
+    /// Whether the failure is likely transient and worth retrying.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            FetchErrorKind::Timeout | FetchErrorKind::Connect => true,
+            FetchErrorKind::HttpStatus { status } => matches!(status, 429 | 500 | 502 | 503 | 504),
+            FetchErrorKind::Dns | FetchErrorKind::Tls | FetchErrorKind::Other => false,
+        }
+    }
+}
+
+/// A URL that could not be fetched, as recorded in the report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchError {
+    pub url: String,
+    #[serde(flatten)]
+    pub kind: FetchErrorKind,
+    pub message: String,
+    /// Number of requests made for the URL, including retries.
+    pub attempts: u32,
+}
+
+impl RetryConfig {
+    /// The delay before retry number `attempt` (1 for the first retry), or
+    /// `None` if the failure should not be retried.
+    ///
+    /// The delay doubles with every attempt up to `max_delay_ms`, with
+    /// random jitter of up to half the delay so that concurrent retries
+    /// spread out. A server's `Retry-After` is honored as a minimum; if it
+    /// asks for longer than `max_delay_ms`, the URL is given up on.
+    pub fn delay(
+        &self,
+        kind: &FetchErrorKind,
+        attempt: u32,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if !kind.is_transient() || attempt > self.max_retries {
+            return None;
+        }
+
+        let max_delay = Duration::from_millis(self.max_delay_ms);
+        let exponential = Duration::from_millis(self.base_delay_ms)
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(max_delay);
+        let backoff = if self.jitter {
+            let half = exponential / 2;
+            half + half.mul_f64(rand::thread_rng().gen::<f64>())
+        } else {
+            exponential
+        };
+
+        match retry_after {
+            Some(wait) if wait > max_delay => None,
+            Some(wait) => Some(wait.max(backoff)),
+            None => Some(backoff),
+        }
+    }
+}
+
+/// Parse a `Retry-After` header: delay-seconds or an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RetryConfig {
+        RetryConfig {
+            max_retries: 3,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+            jitter: false,
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let timeout = FetchErrorKind::Timeout;
+        let delays: Vec<Option<Duration>> = (1..=4).map(|a| config().delay(&timeout, a, None)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(400)),
+                None,
+            ]
+        );
+
+        let not_found = FetchErrorKind::HttpStatus { status: 404 };
+        assert_eq!(config().delay(&not_found, 1, None), None);
+        assert_eq!(config().delay(&FetchErrorKind::Dns, 1, None), None);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let config = RetryConfig {
+            jitter: true,
+            ..config()
+        };
+        for _ in 0..50 {
+            let delay = config.delay(&FetchErrorKind::Connect, 3, None).unwrap();
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let throttled = FetchErrorKind::HttpStatus { status: 429 };
+        assert_eq!(
+            config().delay(&throttled, 1, Some(Duration::from_millis(700))),
+            Some(Duration::from_millis(700))
+        );
+        assert_eq!(config().delay(&throttled, 1, Some(Duration::from_secs(5))), None);
+
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+/// A single Allow/Disallow line.
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// A group of rules that applies to one or more user agents.
+#[derive(Debug, Clone, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<f64>,
+}
+
+/// A parsed robots.txt file (RFC 9309, plus the common `Crawl-delay`
+/// and `Sitemap` extensions).
+#[derive(Debug, Clone, Default)]
+pub struct RobotsTxt {
+    groups: Vec<Group>,
+    /// Absolute URLs from `Sitemap:` lines.
+    pub sitemaps: Vec<String>,
+}
+
+impl RobotsTxt {
+    /// A robots.txt that allows everything, used when the file is missing.
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// A robots.txt that disallows everything, used when the file could
+    /// not be fetched because the server is failing.
+    pub fn disallow_all() -> Self {
+        Self {
+            groups: vec![Group {
+                agents: vec!["*".to_string()],
+                rules: vec![Rule {
+                    allow: false,
+                    pattern: "/".to_string(),
+                }],
+                crawl_delay: None,
+            }],
+            sitemaps: vec![],
+        }
+    }
+
+    pub fn parse(body: &str) -> Self {
+        let mut robots = RobotsTxt::default();
+        let mut current: Option<Group> = None;
+        // True once the current group has seen a rule; a following
+        // user-agent line then starts a new group.
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = match line.find('#') {
+                Some(i) => &line[..i],
+                None => line,
+            };
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules || current.is_none() {
+                        if let Some(group) = current.take() {
+                            robots.groups.push(group);
+                        }
+                        current = Some(Group::default());
+                        in_rules = false;
+                    }
+                    if let Some(group) = current.as_mut() {
+                        group.agents.push(value.to_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow means "allow everything".
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(group) = current.as_mut() {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    if let Some(group) = current.as_mut() {
+                        group.crawl_delay = value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|d| d.is_finite() && *d >= 0.0);
+                    }
+                }
+                "sitemap" if !value.is_empty() => {
+                    robots.sitemaps.push(value.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(group) = current {
+            robots.groups.push(group);
+        }
+        robots
+    }
+
+    /// Whether `user_agent` may fetch `path` (path plus optional query).
+    ///
+    /// The most specific (longest) matching pattern wins; on a tie, Allow wins.
+    pub fn is_allowed(&self, user_agent: &str, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+
+        let mut best: Option<&Rule> = None;
+        for group in self.groups_for(user_agent) {
+            for rule in &group.rules {
+                if !pattern_matches(&rule.pattern, path) {
+                    continue;
+                }
+                best = match best {
+                    Some(b)
+                        if b.pattern.len() > rule.pattern.len()
+                            || (b.pattern.len() == rule.pattern.len() && b.allow) =>
+                    {
+                        Some(b)
+                    }
+                    _ => Some(rule),
+                };
+            }
+        }
+
+        best.is_none_or(|rule| rule.allow)
+    }
+
+    /// The `Crawl-delay` requested for `user_agent`, if any, capped at
+    /// `max`.
+    pub fn crawl_delay(&self, user_agent: &str, max: Duration) -> Option<Duration> {
+        self.groups_for(user_agent)
+            .iter()
+            .filter_map(|g| g.crawl_delay)
+            .reduce(f64::max)
+            .map(|d| Duration::try_from_secs_f64(d).map_or(max, |d| d.min(max)))
+    }
+
+    /// Groups that apply to `user_agent`: those naming the longest agent
+    /// token contained in our product token, or the `*` groups otherwise.
+    fn groups_for(&self, user_agent: &str) -> Vec<&Group> {
+        let token = product_token(user_agent);
+
+        let best_len = self
+            .groups
+            .iter()
+            .flat_map(|g| &g.agents)
+            .filter(|a| a.as_str() != "*" && !a.is_empty() && token.contains(a.as_str()))
+            .map(|a| a.len())
+            .max();
+
+        match best_len {
+            Some(len) => self
+                .groups
+                .iter()
+                .filter(|g| {
+                    g.agents
+                        .iter()
+                        .any(|a| a.len() == len && token.contains(a.as_str()))
+                })
+                .collect(),
+            None => self
+                .groups
+                .iter()
+                .filter(|g| g.agents.iter().any(|a| a == "*"))
+                .collect(),
+        }
+    }
+}
+
+/// The product token of a User-Agent string: `Foo-Bot/1.2 (+url)` → `foo-bot`.
+fn product_token(user_agent: &str) -> String {
+    user_agent
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Match a robots.txt path pattern supporting `*` (any sequence) and a
+/// trailing `$` (end of path). Patterns are otherwise prefix matches.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !path.starts_with(first) {
+        return false;
+    }
+    let mut pos = first.len();
+
+    let rest: Vec<&str> = parts.collect();
+    for (i, part) in rest.iter().enumerate() {
+        let is_last = i == rest.len() - 1;
+        if is_last && anchored {
+            // The final literal must sit at the very end of the path.
+            return path.len() >= pos + part.len() && path.ends_with(part);
+        }
+        match path[pos..].find(part) {
+            Some(found) => pos += found + part.len(),
+            None => return false,
+        }
+    }
+
+    !anchored || pos == path.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UA: &str = "AutonomousWebAgent-POC/0.1";
+    const MAX: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn test_longest_match_wins() {
+        let robots = RobotsTxt::parse(
+            "User-agent: *\nDisallow: /private\nAllow: /private/public\n",
+        );
+        assert!(!robots.is_allowed(UA, "/private/secret"));
+        assert!(robots.is_allowed(UA, "/private/public/page"));
+        assert!(robots.is_allowed(UA, "/about"));
+    }
+
+    #[test]
+    fn test_wildcards_and_anchor() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /*.pdf$\nDisallow: /search*q=\n");
+        assert!(!robots.is_allowed(UA, "/docs/report.pdf"));
+        assert!(robots.is_allowed(UA, "/docs/report.pdf?download=1"));
+        assert!(!robots.is_allowed(UA, "/search?lang=en&q=rust"));
+        assert!(robots.is_allowed(UA, "/search?lang=en"));
+    }
+
+    #[test]
+    fn test_user_agent_group_matching() {
+        let robots = RobotsTxt::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: autonomouswebagent\nDisallow: /admin\nCrawl-delay: 2\n",
+        );
+        assert!(robots.is_allowed(UA, "/products"));
+        assert!(!robots.is_allowed(UA, "/admin/users"));
+        assert_eq!(robots.crawl_delay(UA, MAX), Some(Duration::from_secs(2)));
+
+        assert!(!robots.is_allowed("OtherBot/1.0", "/products"));
+        assert_eq!(robots.crawl_delay("OtherBot/1.0", MAX), None);
+    }
+
+    #[test]
+    fn test_crawl_delay_is_bounded() {
+        let delay = |value: &str| {
+            RobotsTxt::parse(&format!("User-agent: *\nCrawl-delay: {}\n", value)).crawl_delay(UA, MAX)
+        };
+        assert_eq!(delay("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(delay("86400"), Some(MAX));
+        assert_eq!(delay("1e300"), Some(MAX));
+        for invalid in ["inf", "-inf", "NaN", "-1", "soon"] {
+            assert_eq!(delay(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_grouped_user_agents_and_sitemaps() {
+        let robots = RobotsTxt::parse(
+            "# comment\nUser-agent: a\nUser-agent: *\nDisallow: /tmp # trailing\nDisallow:\n\nSitemap: https://example.com/sitemap.xml\n",
+        );
+        assert!(!robots.is_allowed(UA, "/tmp/file"));
+        assert!(robots.is_allowed(UA, "/"));
+        assert_eq!(robots.sitemaps, vec!["https://example.com/sitemap.xml"]);
+    }
+
+    #[test]
+    fn test_disallow_all_still_allows_robots_txt() {
+        let robots = RobotsTxt::disallow_all();
+        assert!(!robots.is_allowed(UA, "/"));
+        assert!(robots.is_allowed(UA, "/robots.txt"));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::{AgentError, Result};
+use flate2::read::GzDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::Read;
+
+/// A parsed sitemap document.
+#[derive(Debug, PartialEq)]
+pub enum Sitemap {
+    /// A `<urlset>` listing page URLs.
+    UrlSet(Vec<String>),
+    /// A `<sitemapindex>` listing further sitemap URLs.
+    Index(Vec<String>),
+}
+
+/// Upper bound on decompressed sitemap size (the sitemap protocol caps
+/// files at 50 MB uncompressed).
+const MAX_SITEMAP_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Parse a sitemap or sitemap index, transparently decompressing gzip
+/// (`sitemap.xml.gz`) bodies.
+pub fn parse(body: &[u8]) -> Result<Sitemap> {
+    let xml = if body.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(body)
+            .take(MAX_SITEMAP_BYTES)
+            .read_to_end(&mut decoded)
+            .map_err(|e| AgentError::Sitemap(format!("gzip decode failed: {}", e)))?;
+        decoded
+    } else {
+        body.to_vec()
+    };
+
+    parse_xml(&xml)
+}
+
+fn parse_xml(xml: &[u8]) -> Result<Sitemap> {
+    let mut reader = Reader::from_reader(xml);
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut is_index: Option<bool> = None;
+    let mut in_loc = false;
+    let mut locs = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"urlset" if is_index.is_none() => is_index = Some(false),
+                b"sitemapindex" if is_index.is_none() => is_index = Some(true),
+                b"loc" => in_loc = true,
+                _ => {}
+            },
+            Ok(Event::End(e)) => {
+                if e.local_name().as_ref() == b"loc" {
+                    in_loc = false;
+                }
+            }
+            Ok(Event::Text(t)) if in_loc => {
+                let text = t
+                    .unescape()
+                    .map_err(|e| AgentError::Sitemap(e.to_string()))?;
+                locs.push(text.trim().to_string());
+            }
+            Ok(Event::CData(t)) if in_loc => {
+                locs.push(String::from_utf8_lossy(&t.into_inner()).trim().to_string());
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                return Err(AgentError::Sitemap(format!(
+                    "XML error at position {}: {}",
+                    reader.buffer_position(),
+                    e
+                )))
+            }
+        }
+        buf.clear();
+    }
+
+    locs.retain(|l| !l.is_empty());
+    match is_index {
+        Some(true) => Ok(Sitemap::Index(locs)),
+        Some(false) => Ok(Sitemap::UrlSet(locs)),
+        None => Err(AgentError::Sitemap(
+            "document is neither <urlset> nor <sitemapindex>".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/a</loc><lastmod>2024-01-01</lastmod></url>
+  <url><loc> https://example.com/b?x=1&amp;y=2 </loc></url>
+</urlset>"#;
+
+    #[test]
+    fn test_parse_urlset() {
+        let sitemap = parse(URLSET.as_bytes()).unwrap();
+        assert_eq!(
+            sitemap,
+            Sitemap::UrlSet(vec![
+                "https://example.com/a".to_string(),
+                "https://example.com/b?x=1&y=2".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let xml = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-1.xml.gz</loc></sitemap>
+</sitemapindex>"#;
+        assert_eq!(
+            parse(xml.as_bytes()).unwrap(),
+            Sitemap::Index(vec!["https://example.com/sitemap-1.xml.gz".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(URLSET.as_bytes()).unwrap();
+        let gz = encoder.finish().unwrap();
+
+        match parse(&gz).unwrap() {
+            Sitemap::UrlSet(urls) => assert_eq!(urls.len(), 2),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_html() {
+        assert!(parse(b"<html><body>Not found</body></html>").is_err());
+    }
+}
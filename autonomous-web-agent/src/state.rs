@@ -0,0 +1,163 @@
+use crate::error::{AgentError, Result};
+use crate::extractor::ExtractedItem;
+use crate::guardrails::BudgetUsage;
+use crate::policy::PolicyDecision;
+use crate::provenance::{Link, PageRecord};
+use crate::redact::RedactionStats;
+use crate::retry::FetchError;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// A snapshot of a crawl's progress, saved so an interrupted or
+/// budget-exhausted crawl can be resumed or inspected.
+///
+/// URLs that were scheduled but not yet processed when the snapshot was
+/// taken are stored in `frontier`, not `visited`, so a resumed crawl
+/// fetches them again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CrawlState {
+    pub task_name: String,
+    /// The id of the run that started the crawl, reported again when it is
+    /// resumed.
+    #[serde(default)]
+    pub run_id: String,
+    pub visited: Vec<String>,
+    /// Pending visits, in the order they will be tried.
+    pub frontier: Vec<PendingVisit>,
+    pub results: Vec<ExtractedItem>,
+    /// Text SimHash of each processed page, for near-duplicate detection.
+    #[serde(default)]
+    pub fingerprints: Vec<(String, u64)>,
+    pub pages_visited: usize,
+    pub pages_skipped: usize,
+    /// Requests counted against the budget, including robots.txt and
+    /// sitemap fetches.
+    pub requests_made: usize,
+    /// Bytes received and per-domain and per-path counts.
+    #[serde(default)]
+    pub usage: BudgetUsage,
+    pub safety_blocks: Vec<String>,
+    /// URLs the URL policy denied.
+    #[serde(default)]
+    pub policy_denials: Vec<PolicyDecision>,
+    /// Sensitive data redacted from the results so far.
+    #[serde(default)]
+    pub redactions: RedactionStats,
+    /// URLs that could not be fetched.
+    #[serde(default)]
+    pub errors: Vec<FetchError>,
+    /// Audit records of the URLs considered so far.
+    #[serde(default)]
+    pub pages: Vec<PageRecord>,
+    /// Links found on processed pages.
+    #[serde(default)]
+    pub links: Vec<Link>,
+}
+
+/// A URL waiting in the frontier of a saved crawl.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingVisit {
+    pub url: String,
+    pub depth: usize,
+    /// The frontier scorer's score for the URL.
+    #[serde(default)]
+    pub score: f64,
+    /// The page the URL was first found on.
+    #[serde(default)]
+    pub referrer: Option<String>,
+}
+
+impl CrawlState {
+    /// Load a state file written by `save`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| AgentError::State(format!("cannot read {}: {}", path.display(), e)))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| AgentError::State(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Write the state to `path`.
+    ///
+    /// The state is written to a temporary file and renamed into place, so
+    /// a crash mid-write never leaves a truncated state file behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let write = || -> std::io::Result<()> {
+            let mut tmp_path = path.as_os_str().to_owned();
+            tmp_path.push(".tmp");
+            {
+                let mut writer = BufWriter::new(File::create(&tmp_path)?);
+                serde_json::to_writer(&mut writer, self)?;
+                writer.flush()?;
+            }
+            fs::rename(&tmp_path, path)
+        };
+        write().map_err(|e| AgentError::State(format!("cannot write {}: {}", path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::FetchErrorKind;
+    use serde_json::Value;
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let path = std::env::temp_dir().join(format!("crawl-state-{}.json", std::process::id()));
+        let state = CrawlState {
+            task_name: "docs".to_string(),
+            run_id: "0123456789abcdef".to_string(),
+            visited: vec!["https://example.com/".to_string()],
+            frontier: vec![PendingVisit {
+                url: "https://example.com/a".to_string(),
+                depth: 1,
+                score: -0.5,
+                referrer: Some("https://example.com/".to_string()),
+            }],
+            results: vec![ExtractedItem {
+                source_url: "https://example.com/".to_string(),
+                field: "h1".to_string(),
+                value: Value::String("Example".to_string()),
+            }],
+            fingerprints: vec![("https://example.com/".to_string(), u64::MAX)],
+            pages_visited: 1,
+            pages_skipped: 0,
+            requests_made: 2,
+            usage: BudgetUsage::default(),
+            safety_blocks: vec![],
+            policy_denials: vec![],
+            redactions: RedactionStats::default(),
+            errors: vec![FetchError {
+                url: "https://example.com/b".to_string(),
+                kind: FetchErrorKind::HttpStatus { status: 503 },
+                message: "HTTP 503".to_string(),
+                attempts: 3,
+            }],
+            pages: vec![],
+            links: vec![Link {
+                from: "https://example.com/".to_string(),
+                to: "https://example.com/a".to_string(),
+            }],
+        };
+
+        state.save(&path).unwrap();
+        let loaded = CrawlState::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.task_name, "docs");
+        assert_eq!(loaded.run_id, state.run_id);
+        assert_eq!(loaded.frontier, state.frontier);
+        assert_eq!(loaded.results[0].value_text(), "Example");
+        assert_eq!(loaded.requests_made, 2);
+        assert_eq!(loaded.fingerprints[0].1, u64::MAX);
+        assert_eq!(loaded.errors[0].kind, FetchErrorKind::HttpStatus { status: 503 });
+        assert_eq!(loaded.links, state.links);
+    }
+
+    #[test]
+    fn test_load_missing_file_fails() {
+        assert!(CrawlState::load(Path::new("/nonexistent/state.json")).is_err());
+    }
+}
@@ -1,12 +1,164 @@
+use crate::forms::FormRequest;
+use crate::transform::{Pattern, Transform};
 use regex::Regex;
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::str::FromStr;
 use url::Url;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ExtractionGoal {
     CollectLinks,
     ExtractText { selectors: Vec<String> },
+    /// Extract the text of every element matching each field's selector.
+    /// Values are plain strings; for transforms, use `ExtractRecords` with
+    /// `body` as the container.
     ExtractStructured { fields: HashMap<String, String> },
+    /// Extract one record per element matching `container`, evaluating
+    /// each field's selector relative to that element.
+    ExtractRecords {
+        #[serde(default = "default_record_name")]
+        name: String,
+        container: String,
+        fields: Vec<FieldSpec>,
+    },
+    /// Extract the page's main content as one `article` item, with its
+    /// title, byline and publish date, leaving out boilerplate.
+    ExtractArticle,
+    /// Extract the structured data the page publishes: schema.org JSON-LD,
+    /// microdata and RDFa items, and OpenGraph and Twitter card tags, one
+    /// item per block under the name of its source.
+    ExtractMetadata {
+        /// Keep only schema.org items of these types, e.g. `Product`.
+        #[serde(default)]
+        types: Vec<String>,
+    },
+}
+
+fn default_record_name() -> String {
+    "record".to_string()
+}
+
+/// A field of a record, evaluated relative to the record's container element.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldSpec {
+    pub name: String,
+    /// CSS selector relative to the container. When absent, the container
+    /// element itself is used.
+    #[serde(default)]
+    pub selector: Option<String>,
+    /// Read this attribute (e.g. `href`, `src`, `content`) instead of the
+    /// element's text.
+    #[serde(default)]
+    pub attribute: Option<String>,
+    /// Records missing a required field are dropped; missing optional
+    /// fields are emitted as `null`.
+    #[serde(default)]
+    pub required: bool,
+    /// Post-processing applied in order to the raw value, e.g. to turn
+    /// `"$1,299.00"` into a typed price.
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+}
+
+/// Parses the CLI shorthand `name=selector[@attribute][!][|transform...]`,
+/// where `!` marks the field as required and each `|`-separated suffix is
+/// a transform such as `ws`, `number` or `regex:<pattern>`.
+impl FromStr for FieldSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, rest) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid field '{}', expected name=selector", s))?;
+
+        let mut parts = rest.split('|');
+        let rest = parts.next().unwrap_or("");
+        let transforms = parts.map(str::parse).collect::<Result<Vec<Transform>, _>>()?;
+
+        let (rest, required) = match rest.strip_suffix('!') {
+            Some(r) => (r, true),
+            None => (rest, false),
+        };
+        let (selector, attribute) = match rest.rsplit_once('@') {
+            Some((sel, attr)) => (sel, Some(attr.to_string())),
+            None => (rest, None),
+        };
+
+        Ok(FieldSpec {
+            name: name.trim().to_string(),
+            selector: Some(selector.trim())
+                .filter(|s| !s.is_empty())
+                .map(str::to_string),
+            attribute,
+            required,
+            transforms,
+        })
+    }
+}
+
+/// A form the agent fills in and submits wherever it finds it.
+///
+/// Submissions are still subject to `SafetyConfig::allowed_form_actions`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FormSubmission {
+    /// CSS selector for the form, e.g. `form#search`.
+    pub selector: String,
+    /// Only submit the form on pages whose URL matches this pattern.
+    #[serde(default)]
+    pub page_pattern: Option<Pattern>,
+    /// Field values, overriding the form's defaults.
+    #[serde(default)]
+    pub values: HashMap<String, String>,
+}
+
+/// How to follow multi-page result sets.
+///
+/// Pages reached by pagination keep the depth of the page the series
+/// started on and are visited in order, ahead of ordinary links. The next
+/// page is found with, in order of preference: `url_template`,
+/// `next_selector`, then a `rel="next"` link.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Pagination {
+    /// CSS selector for the "next page" link, e.g. `a.next`.
+    #[serde(default)]
+    pub next_selector: Option<String>,
+    /// URL with a `{page}` placeholder, e.g. `https://example.com/list?page={page}`.
+    /// Pages are requested one after another until one fails or
+    /// `max_pages` is reached. Requires `page_pattern`.
+    #[serde(default)]
+    pub url_template: Option<String>,
+    /// The page number of the first page of a series, for `url_template`.
+    #[serde(default = "default_start_page")]
+    pub start_page: usize,
+    /// Maximum number of pages in a series, including the first.
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+    /// Start series on pages whose URL matches this pattern. Without one,
+    /// only the seed pages start a series.
+    #[serde(default)]
+    pub page_pattern: Option<Pattern>,
+}
+
+fn default_start_page() -> usize {
+    1
+}
+
+fn default_max_pages() -> usize {
+    10
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            next_selector: None,
+            url_template: None,
+            start_page: default_start_page(),
+            max_pages: default_max_pages(),
+            page_pattern: None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -15,10 +167,83 @@ pub struct Task {
     pub seed_urls: Vec<Url>,
     pub goal: ExtractionGoal,
     pub link_follow_patterns: Vec<Regex>,
+    /// Links matching any of these are never followed, even if they match
+    /// a follow pattern.
+    pub link_exclude_patterns: Vec<Regex>,
+    /// Seed the frontier from the sitemaps of the seed URLs' sites
+    /// (robots.txt `Sitemap:` lines, falling back to `/sitemap.xml`).
+    pub discover_sitemaps: bool,
+    pub forms: Vec<FormSubmission>,
+    pub pagination: Option<Pagination>,
 }
 
 #[derive(Debug)]
 pub enum AgentAction {
     Visit(Url, usize),
+    Submit(FormRequest, usize),
+    /// Visit the given page number of a paginated series.
+    NextPage(Url, usize, usize),
     Done,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_spec_shorthand() {
+        let field: FieldSpec = "price=.price@content!|ws|number".parse().unwrap();
+        assert_eq!(field.name, "price");
+        assert_eq!(field.selector.as_deref(), Some(".price"));
+        assert_eq!(field.attribute.as_deref(), Some("content"));
+        assert!(field.required);
+        assert_eq!(field.transforms.len(), 2);
+
+        // An empty selector means the container element itself.
+        let field: FieldSpec = " link = @href".parse().unwrap();
+        assert_eq!(field.name, "link");
+        assert_eq!(field.selector, None);
+        assert_eq!(field.attribute.as_deref(), Some("href"));
+        assert!(!field.required);
+
+        assert!("no-selector".parse::<FieldSpec>().is_err());
+        assert!("x=.x|bogus".parse::<FieldSpec>().is_err());
+    }
+
+    #[test]
+    fn test_goal_and_pagination_defaults() {
+        let goal: ExtractionGoal = serde_json::from_str(
+            r#"{"type": "extract_records", "container": ".item", "fields": [{"name": "title"}]}"#,
+        )
+        .unwrap();
+        let ExtractionGoal::ExtractRecords { name, fields, .. } = goal else {
+            panic!("expected extract_records");
+        };
+        assert_eq!(name, "record");
+        assert!(fields[0].selector.is_none() && fields[0].transforms.is_empty());
+
+        let goal: ExtractionGoal = serde_json::from_str(r#"{"type": "extract_metadata"}"#).unwrap();
+        assert!(matches!(goal, ExtractionGoal::ExtractMetadata { types } if types.is_empty()));
+        assert!(serde_json::from_str::<ExtractionGoal>(r#"{"type": "summarize"}"#).is_err());
+
+        let pagination: Pagination =
+            serde_json::from_str(r#"{"next_selector": "a.next"}"#).unwrap();
+        assert_eq!(pagination.start_page, 1);
+        assert_eq!(pagination.max_pages, 10);
+        assert!(serde_json::from_str::<Pagination>(r#"{"page_pattern": "("}"#).is_err());
+    }
+
+    #[test]
+    fn test_form_submission_values() {
+        let form: FormSubmission = serde_json::from_str(
+            r#"{"selector": "form#search", "page_pattern": "/search$", "values": {"q": "rust"}}"#,
+        )
+        .unwrap();
+        assert_eq!(form.values["q"], "rust");
+        assert!(form
+            .page_pattern
+            .unwrap()
+            .0
+            .is_match("https://example.com/search"));
+    }
+}
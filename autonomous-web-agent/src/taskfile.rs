@@ -0,0 +1,277 @@
+use crate::config::{
+    AgentConfig, CacheConfig, ContentConfig, DedupConfig, FrontierConfig, RedactionConfig,
+    RetryConfig, SafetyConfig,
+};
+use crate::error::{AgentError, Result};
+use crate::task::{ExtractionGoal, FormSubmission, Pagination, Task};
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use url::Url;
+
+/// A declarative task definition, as found in a task file.
+///
+/// ```yaml
+/// name: Docs headings
+/// seeds: [https://example.com/docs/]
+/// safety:
+///   allowed_domains: [example.com]
+///   max_depth: 2
+///   max_requests: 50
+///   policy:
+///     rules:
+///       - { name: no-admin, action: deny, paths: ['/admin/**'] }
+/// follow_patterns: ['/docs/']
+/// exclude_patterns: ['\.pdf$']
+/// extract:
+///   type: extract_text
+///   selectors: [h1, h2]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskSpec {
+    pub name: String,
+    pub seeds: Vec<String>,
+    #[serde(default)]
+    pub safety: SafetyConfig,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub frontier: FrontierConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub content: ContentConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub follow_patterns: Vec<String>,
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    #[serde(default = "default_goal")]
+    pub extract: ExtractionGoal,
+    #[serde(default)]
+    pub discover_sitemaps: bool,
+    #[serde(default)]
+    pub forms: Vec<FormSubmission>,
+    #[serde(default)]
+    pub pagination: Option<Pagination>,
+}
+
+fn default_goal() -> ExtractionGoal {
+    ExtractionGoal::ExtractText {
+        selectors: ["h1", "h2", "h3", "p"].iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/// A task file holds either a single task or a list of them under `tasks`.
+#[derive(Deserialize)]
+struct TaskList {
+    tasks: Vec<TaskSpec>,
+}
+
+impl TaskSpec {
+    /// Build a runnable `Task`, validating seed URLs and patterns.
+    pub fn to_task(&self) -> Result<Task> {
+        let seed_urls = self
+            .seeds
+            .iter()
+            .map(|s| {
+                Url::parse(s).map_err(|e| {
+                    AgentError::TaskFile(format!("task '{}': invalid seed URL '{}': {}", self.name, s, e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if seed_urls.is_empty() {
+            return Err(AgentError::TaskFile(format!(
+                "task '{}': at least one seed URL is required",
+                self.name
+            )));
+        }
+
+        // A template applies to any page, so without a pattern every page
+        // crawled would start its own series.
+        if self
+            .pagination
+            .as_ref()
+            .is_some_and(|p| p.url_template.is_some() && p.page_pattern.is_none())
+        {
+            return Err(AgentError::TaskFile(format!(
+                "task '{}': pagination url_template requires a page_pattern",
+                self.name
+            )));
+        }
+
+        Ok(Task {
+            name: self.name.clone(),
+            seed_urls,
+            goal: self.extract.clone(),
+            link_follow_patterns: compile_patterns(&self.name, &self.follow_patterns)?,
+            link_exclude_patterns: compile_patterns(&self.name, &self.exclude_patterns)?,
+            discover_sitemaps: self.discover_sitemaps,
+            forms: self.forms.clone(),
+            pagination: self.pagination.clone(),
+        })
+    }
+
+    /// Agent configuration for this task: defaults plus the task's safety limits.
+    pub fn agent_config(&self) -> AgentConfig {
+        AgentConfig {
+            safety: self.safety.clone(),
+            dedup: self.dedup.clone(),
+            frontier: self.frontier.clone(),
+            cache: self.cache.clone(),
+            retry: self.retry.clone(),
+            content: self.content.clone(),
+            redaction: self.redaction.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+fn compile_patterns(task_name: &str, patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|p| {
+            Regex::new(p).map_err(|e| {
+                AgentError::TaskFile(format!("task '{}': invalid pattern '{}': {}", task_name, p, e))
+            })
+        })
+        .collect()
+}
+
+/// Load task definitions from a YAML (`.yaml`/`.yml`), TOML (`.toml`) or
+/// JSON (`.json`) file.
+pub fn load(path: &Path) -> Result<Vec<TaskSpec>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| AgentError::TaskFile(format!("cannot read {}: {}", path.display(), e)))?;
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    parse(&contents, &extension)
+        .map_err(|e| AgentError::TaskFile(format!("{}: {}", path.display(), e)))
+}
+
+/// Parse task definitions from a string in the format named by `extension`.
+fn parse(contents: &str, extension: &str) -> std::result::Result<Vec<TaskSpec>, String> {
+    // Normalize every format to a JSON value first so that single-task and
+    // multi-task files are told apart the same way, with field-level errors.
+    let value: serde_json::Value = match extension {
+        "yaml" | "yml" => serde_yaml::from_str(contents).map_err(|e| e.to_string())?,
+        "toml" => toml::from_str(contents).map_err(|e| e.to_string())?,
+        "json" => serde_json::from_str(contents).map_err(|e| e.to_string())?,
+        other => {
+            return Err(format!(
+                "unsupported task file extension '{}' (expected yaml, yml, toml or json)",
+                other
+            ))
+        }
+    };
+
+    let tasks = if value.get("tasks").is_some() {
+        serde_json::from_value::<TaskList>(value).map(|list| list.tasks)
+    } else {
+        serde_json::from_value::<TaskSpec>(value).map(|task| vec![task])
+    };
+    tasks.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_yaml_task() {
+        let yaml = r#"
+name: Docs
+seeds: [https://example.com/docs/]
+safety:
+  allowed_domains: [example.com]
+  max_requests: 5
+  policy:
+    rules:
+      - { action: deny, paths: ['/admin/**'], query_params: [session] }
+exclude_patterns: ['\.pdf$']
+extract:
+  type: extract_structured
+  fields: { title: h1 }
+"#;
+        let specs = parse(yaml, "yaml").unwrap();
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].safety.max_requests, 5);
+        // Unspecified safety limits keep their defaults.
+        assert_eq!(specs[0].safety.max_depth, SafetyConfig::default().max_depth);
+        assert_eq!(specs[0].safety.policy.rules[0].paths, vec!["/admin/**"]);
+        assert!(specs[0].safety.policy.block_private_addresses);
+
+        let task = specs[0].to_task().unwrap();
+        assert!(task.link_exclude_patterns[0].is_match("https://example.com/a.pdf"));
+        assert!(matches!(task.goal, ExtractionGoal::ExtractStructured { .. }));
+    }
+
+    #[test]
+    fn test_parse_multiple_toml_tasks() {
+        let toml = r#"
+[[tasks]]
+name = "one"
+seeds = ["https://example.com/"]
+
+[[tasks]]
+name = "two"
+seeds = ["https://example.org/"]
+extract = { type = "collect_links" }
+"#;
+        let specs = parse(toml, "toml").unwrap();
+        assert_eq!(specs.len(), 2);
+        assert!(matches!(specs[0].extract, ExtractionGoal::ExtractText { .. }));
+        assert!(matches!(specs[1].extract, ExtractionGoal::CollectLinks));
+    }
+
+    #[test]
+    fn test_parse_record_transforms() {
+        let yaml = r#"
+name: Prices
+seeds: [https://shop.example/]
+extract:
+  type: extract_records
+  container: .product
+  fields:
+    - name: price
+      selector: .price
+      transforms: [{op: normalize_whitespace}, {op: regex, pattern: '([\d.,]+)'}, {op: number}]
+"#;
+        let specs = parse(yaml, "yaml").unwrap();
+        match &specs[0].extract {
+            ExtractionGoal::ExtractRecords { fields, .. } => assert_eq!(fields[0].transforms.len(), 3),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let bad = yaml.replace(r"([\d.,]+)", "(");
+        assert!(parse(&bad, "yaml").is_err());
+    }
+
+    #[test]
+    fn test_invalid_tasks_are_rejected() {
+        assert!(parse(r#"{"name": "x", "seeds": [], "bogus": 1}"#, "json").is_err());
+        assert!(parse("name: x", "ini").is_err());
+
+        let specs = parse(r#"{"name": "x", "seeds": ["not a url"]}"#, "json").unwrap();
+        assert!(specs[0].to_task().is_err());
+
+        let paginated = |pagination: &str| {
+            let yaml = format!("name: x\nseeds: [https://example.com/list]\npagination: {}", pagination);
+            parse(&yaml, "yaml").unwrap()[0].to_task()
+        };
+        let error = paginated("{url_template: '?page={page}'}").unwrap_err();
+        assert!(error.to_string().contains("page_pattern"), "{}", error);
+        assert!(paginated("{url_template: '?page={page}', page_pattern: '/list$'}").is_ok());
+    }
+}
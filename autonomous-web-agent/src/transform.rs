@@ -0,0 +1,351 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Number, Value};
+use std::str::FromStr;
+use url::Url;
+
+/// A post-processing step applied to an extracted field value.
+///
+/// Transforms run in order; each takes the previous step's output. A step
+/// that cannot handle its input (a regex that does not match, text that is
+/// not a number) fails the field, which is then treated as missing.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Transform {
+    /// Strip leading and trailing whitespace.
+    Trim,
+    /// Collapse runs of whitespace into single spaces and trim.
+    NormalizeWhitespace,
+    /// Replace the value with a capture group of the first match.
+    Regex {
+        pattern: Pattern,
+        #[serde(default = "default_group")]
+        group: usize,
+    },
+    /// Parse a number, tolerating thousands separators and a decimal comma.
+    Number,
+    /// Parse a price into `{"amount": .., "currency": ..}`.
+    Currency,
+    /// Parse a date or timestamp into ISO 8601. Without `format`, a set of
+    /// common formats is tried.
+    Date {
+        #[serde(default)]
+        format: Option<String>,
+    },
+    /// Resolve a relative URL against the page URL.
+    AbsoluteUrl,
+}
+
+fn default_group() -> usize {
+    1
+}
+
+/// A regex compiled when the task is loaded, so bad patterns are reported
+/// up front rather than failing every field silently.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern(pub Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Regex::new(&s).map(Pattern)
+    }
+}
+
+/// Parses the CLI form of a transform: `trim`, `ws`, `number`, `currency`,
+/// `url`, `date`, `date:<format>` or `regex:<pattern>`.
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (op, arg) = match s.split_once(':') {
+            Some((op, arg)) => (op, Some(arg)),
+            None => (s, None),
+        };
+
+        match (op.trim(), arg) {
+            ("trim", None) => Ok(Transform::Trim),
+            ("ws" | "normalize_whitespace", None) => Ok(Transform::NormalizeWhitespace),
+            ("number", None) => Ok(Transform::Number),
+            ("currency", None) => Ok(Transform::Currency),
+            ("url" | "absolute_url", None) => Ok(Transform::AbsoluteUrl),
+            ("date", format) => Ok(Transform::Date {
+                format: format.map(str::to_string),
+            }),
+            ("regex", Some(pattern)) => Regex::new(pattern)
+                .map(|re| Transform::Regex {
+                    pattern: Pattern(re),
+                    group: default_group(),
+                })
+                .map_err(|e| format!("invalid regex '{}': {}", pattern, e)),
+            _ => Err(format!("unknown transform '{}'", s)),
+        }
+    }
+}
+
+impl Transform {
+    /// Apply this transform, returning `None` if the value cannot be converted.
+    pub fn apply(&self, value: Value, base: &Url) -> Option<Value> {
+        let text = match &value {
+            Value::String(s) => s.as_str(),
+            _ => return None,
+        };
+
+        match self {
+            Transform::Trim => Some(Value::String(text.trim().to_string())),
+            Transform::NormalizeWhitespace => Some(Value::String(
+                text.split_whitespace().collect::<Vec<_>>().join(" "),
+            )),
+            Transform::Regex { pattern, group } => pattern
+                .0
+                .captures(text)
+                .and_then(|caps| caps.get(*group))
+                .map(|m| Value::String(m.as_str().to_string())),
+            Transform::Number => parse_number(text).and_then(number_value),
+            Transform::Currency => {
+                let amount = parse_number(text).and_then(number_value)?;
+                Some(json!({ "amount": amount, "currency": currency_code(text) }))
+            }
+            Transform::Date { format } => {
+                parse_date(text.trim(), format.as_deref()).map(Value::String)
+            }
+            Transform::AbsoluteUrl => base
+                .join(text.trim())
+                .ok()
+                .map(|u| Value::String(u.to_string())),
+        }
+    }
+}
+
+/// Run a pipeline of transforms over a raw string value.
+pub fn apply_all(transforms: &[Transform], raw: String, base: &Url) -> Option<Value> {
+    transforms
+        .iter()
+        .try_fold(Value::String(raw), |value, t| t.apply(value, base))
+}
+
+/// Parse the first number in `text`. When both `,` and `.` appear the later
+/// one is the decimal separator; a lone `,` is a decimal separator unless
+/// it is followed by exactly three digits.
+fn parse_number(text: &str) -> Option<f64> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let negative = text[..start].trim_end().ends_with('-');
+    let digits: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == ',' || *c == '.' || *c == ' ' || *c == '\u{a0}')
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let digits = digits.trim_end_matches([',', '.']);
+
+    let last_comma = digits.rfind(',');
+    let last_dot = digits.rfind('.');
+    let decimal = match (last_comma, last_dot) {
+        (Some(c), Some(d)) => Some(c.max(d)),
+        (Some(c), None) if digits.matches(',').count() == 1 && digits.len() - c - 1 != 3 => Some(c),
+        (None, Some(d)) if digits.matches('.').count() == 1 => Some(d),
+        _ => None,
+    };
+
+    let normalized: String = digits
+        .char_indices()
+        .filter_map(|(i, c)| match c {
+            _ if Some(i) == decimal => Some('.'),
+            ',' | '.' => None,
+            c => Some(c),
+        })
+        .collect();
+
+    let n: f64 = normalized.parse().ok()?;
+    Some(if negative { -n } else { n })
+}
+
+/// Integral values become JSON integers, everything else a float.
+fn number_value(n: f64) -> Option<Value> {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Some(Value::Number(Number::from(n as i64)))
+    } else {
+        Number::from_f64(n).map(Value::Number)
+    }
+}
+
+/// Active ISO 4217 currency codes.
+const CURRENCY_CODES: &str = "AED AFN ALL AMD ANG AOA ARS AUD AWG AZN BAM BBD BDT BGN BHD BIF BMD \
+    BND BOB BRL BSD BTN BWP BYN BZD CAD CDF CHF CLP CNY COP CRC CUP CVE CZK DJF DKK DOP DZD EGP \
+    ERN ETB EUR FJD FKP GBP GEL GHS GIP GMD GNF GTQ GYD HKD HNL HTG HUF IDR ILS INR IQD IRR ISK \
+    JMD JOD JPY KES KGS KHR KMF KPW KRW KWD KYD KZT LAK LBP LKR LRD LSL LYD MAD MDL MGA MKD MMK \
+    MNT MOP MRU MUR MVR MWK MXN MYR MZN NAD NGN NIO NOK NPR NZD OMR PAB PEN PGK PHP PKR PLN PYG \
+    QAR RON RSD RUB RWF SAR SBD SCR SDG SEK SGD SHP SLE SOS SRD SSP STN SVC SYP SZL THB TJS TMT \
+    TND TOP TRY TTD TWD TZS UAH UGX USD UYU UZS VES VND VUV WST XAF XCD XOF XPF YER ZAR ZMW ZWL";
+
+/// The ISO 4217 code for a price string's currency, if any: a known code
+/// right before or after the amount, else a currency symbol.
+fn currency_code(text: &str) -> Value {
+    const SYMBOLS: &[(&str, &str)] = &[
+        ("US$", "USD"),
+        ("€", "EUR"),
+        ("£", "GBP"),
+        ("¥", "JPY"),
+        ("₹", "INR"),
+        ("$", "USD"),
+    ];
+
+    if let Some(start) = text.find(|c: char| c.is_ascii_digit()) {
+        let end = text[start..]
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, ',' | '.' | ' ' | '\u{a0}')))
+            .map_or(text.len(), |i| start + i);
+        let not_letter = |c: char| !c.is_ascii_alphabetic();
+        let before = text[..start].trim_end().rsplit(not_letter).next();
+        let after = text[end..].trim_start().split(not_letter).next();
+        if let Some(code) = [before, after]
+            .into_iter()
+            .flatten()
+            .find(|w| w.len() == 3 && CURRENCY_CODES.split_whitespace().any(|code| code == *w))
+        {
+            return Value::String(code.to_string());
+        }
+    }
+    SYMBOLS
+        .iter()
+        .find(|(symbol, _)| text.contains(symbol))
+        .map(|(_, code)| Value::String(code.to_string()))
+        .unwrap_or(Value::Null)
+}
+
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%B %d, %Y",
+    "%b %d, %Y",
+    "%d %B %Y",
+    "%d %b %Y",
+];
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// Parse a date or timestamp, returning `YYYY-MM-DD` for dates and an
+/// RFC 3339 / ISO 8601 string for timestamps.
+pub(crate) fn parse_date(text: &str, format: Option<&str>) -> Option<String> {
+    if let Some(format) = format {
+        return DateTime::parse_from_str(text, format)
+            .map(|dt| dt.to_rfc3339())
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(text, format)
+                    .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S").to_string())
+            })
+            .or_else(|_| NaiveDate::parse_from_str(text, format).map(|d| d.to_string()))
+            .ok();
+    }
+
+    if let Ok(dt) =
+        DateTime::parse_from_rfc3339(text).or_else(|_| DateTime::parse_from_rfc2822(text))
+    {
+        return Some(dt.to_rfc3339());
+    }
+    if let Some(dt) = DATETIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+    {
+        return Some(dt.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    DATE_FORMATS
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(text, f).ok())
+        .map(|d| d.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://shop.example/list/page.html").unwrap()
+    }
+
+    fn run(transforms: &[Transform], raw: &str) -> Option<Value> {
+        apply_all(transforms, raw.to_string(), &base())
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parse_number("1,234"), Some(1234.0));
+        assert_eq!(parse_number("1,234.50"), Some(1234.5));
+        assert_eq!(parse_number("1.234,50 €"), Some(1234.5));
+        assert_eq!(parse_number("12,5"), Some(12.5));
+        assert_eq!(parse_number("- 3 pts"), Some(-3.0));
+        assert_eq!(parse_number("no digits"), None);
+
+        assert_eq!(run(&[Transform::Number], "42 reviews"), Some(json!(42)));
+    }
+
+    #[test]
+    fn test_currency() {
+        assert_eq!(
+            run(&[Transform::Currency], "Now $1,299.99!"),
+            Some(json!({"amount": 1299.99, "currency": "USD"}))
+        );
+        assert_eq!(
+            run(&[Transform::Currency], "15,00 EUR"),
+            Some(json!({"amount": 15, "currency": "EUR"}))
+        );
+        assert_eq!(
+            run(&[Transform::Currency], "USD 5"),
+            Some(json!({"amount": 5, "currency": "USD"}))
+        );
+        // Capitalized words are not codes unless known and next to the amount.
+        assert_eq!(
+            run(&[Transform::Currency], "NEW $5"),
+            Some(json!({"amount": 5, "currency": "USD"}))
+        );
+        assert_eq!(
+            run(&[Transform::Currency], "ALL SIZES 12 CHF"),
+            Some(json!({"amount": 12, "currency": "CHF"}))
+        );
+        assert_eq!(
+            run(&[Transform::Currency], "NEW 5 PCS"),
+            Some(json!({"amount": 5, "currency": null}))
+        );
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(
+            parse_date("March 5, 2024", None).as_deref(),
+            Some("2024-03-05")
+        );
+        assert_eq!(
+            parse_date("2024-03-05 14:30", None).as_deref(),
+            Some("2024-03-05T14:30:00")
+        );
+        assert_eq!(
+            parse_date("2024-03-05T14:30:00Z", None).as_deref(),
+            Some("2024-03-05T14:30:00+00:00")
+        );
+        assert_eq!(
+            parse_date("05.03.2024", Some("%d.%m.%Y")).as_deref(),
+            Some("2024-03-05")
+        );
+        assert_eq!(parse_date("yesterday", None), None);
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let transforms: Vec<Transform> = ["ws", r"regex:SKU:\s*(\w+)"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(
+            run(&transforms, "  Item\n  SKU:   AB12 "),
+            Some(json!("AB12"))
+        );
+        assert_eq!(run(&transforms, "no sku here"), None);
+
+        assert_eq!(
+            run(&[Transform::AbsoluteUrl], "../p/kettle"),
+            Some(json!("https://shop.example/p/kettle"))
+        );
+        assert!("regex:(".parse::<Transform>().is_err());
+        assert!("bogus".parse::<Transform>().is_err());
+    }
+}